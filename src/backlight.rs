@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use zbus::{blocking::Connection, dbus_proxy};
+
+use crate::AppletError;
+
+const BACKLIGHT_CLASS: &str = "/sys/class/backlight";
+
+// # DBus interface proxy for: `org.freedesktop.login1.Session`
+// Only the method needed to change backlight brightness without root is declared.
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Session",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait LoginSession {
+    /// SetBrightness method
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> zbus::Result<()>;
+}
+
+// a backlight device from sysfs, written through logind so no special permissions are needed.
+pub struct Backlight {
+    name: String,
+    path: PathBuf,
+    max_brightness: u32,
+    proxy: LoginSessionProxyBlocking<'static>,
+}
+
+fn read_u32(path: &Path) -> Result<u32, AppletError> {
+    let text = std::fs::read_to_string(path)?;
+    text.trim()
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+}
+
+impl Backlight {
+    // returns the first backlight device, or None if the system has none.
+    pub fn detect() -> Result<Option<Self>, AppletError> {
+        let mut entries = match std::fs::read_dir(BACKLIGHT_CLASS) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        entries.sort();
+        let Some(path) = entries.into_iter().next() else {
+            return Ok(None);
+        };
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let max_brightness = read_u32(&path.join("max_brightness"))?;
+        if max_brightness == 0 {
+            return Ok(None);
+        }
+        let connection = Connection::system()?;
+        let proxy = LoginSessionProxyBlocking::new(&connection)?;
+        Ok(Some(Self {
+            name,
+            path,
+            max_brightness,
+            proxy,
+        }))
+    }
+
+    // current brightness as 0.0 - 1.0
+    pub fn value(&self) -> Result<f64, AppletError> {
+        let brightness = read_u32(&self.path.join("brightness"))?;
+        Ok(brightness as f64 / self.max_brightness as f64)
+    }
+
+    // set brightness from 0.0 - 1.0
+    pub fn set_value(&self, value: f64) -> zbus::Result<()> {
+        let brightness = (value.clamp(0.0, 1.0) * self.max_brightness as f64).round() as u32;
+        self.proxy
+            .set_brightness("backlight", &self.name, brightness)
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use slint::{
+    platform::{
+        software_renderer::{MinimalSoftwareWindow, RepaintBufferType},
+        Platform, WindowAdapter,
+    },
+    ComponentHandle,
+};
+
+use crate::{
+    config::{Config, Link, Rounding, Strength},
+    connect_window,
+    convert::{dbus_brightness_to_ui_value, dbus_gamma_to_ui_value, dbus_temperature_to_ui_value},
+    guard_changes,
+    pending::Deltas,
+    sync_backlight, sync_worker,
+    worker::{mock::MockWorker, DaemonState, Worker},
+    AppletError, Commit, Parameters, Reconcile, SettingState, Settings, Shared, Ticker,
+    WlGammaRelayApplet,
+};
+
+// renders into memory, so windows can be created without a display.
+pub struct Headless;
+
+impl Platform for Headless {
+    fn create_window_adapter(&self) -> Result<Rc<dyn WindowAdapter>, slint::PlatformError> {
+        Ok(MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer))
+    }
+}
+
+// an applet wired up as in main, talking to a mock daemon on this thread.
+pub fn headless_applet(
+    daemon: DaemonState,
+) -> Result<(WlGammaRelayApplet, Shared, MockWorker), AppletError> {
+    // the platform is set once per thread; later applets on a thread reuse it.
+    let _ = slint::platform::set_platform(Box::new(Headless));
+    let app = WlGammaRelayApplet::new()?;
+    let (worker, mock) = Worker::mock(daemon);
+
+    let temperature = dbus_temperature_to_ui_value(daemon.temperature);
+    let brightness = dbus_brightness_to_ui_value(daemon.brightness);
+    let gamma = dbus_gamma_to_ui_value(daemon.gamma);
+    app.global::<Parameters>().set_invert(daemon.inverted);
+    app.global::<Parameters>().set_grayscale(daemon.grayscale);
+    app.global::<Parameters>()
+        .set_temperature(temperature as f32);
+    app.global::<Parameters>().set_brightness(brightness as f32);
+    app.global::<Parameters>().set_gamma(gamma as f32);
+
+    // values are in ui units and defaults in daemon units, as in main.
+    let setting = |value, default| SettingState {
+        value,
+        delta_accumulation: 0.0,
+        default,
+    };
+    let settings = Settings {
+        temperature: setting(temperature, daemon.temperature as f64),
+        brightness: setting(brightness, daemon.brightness),
+        gamma: setting(gamma, daemon.gamma),
+        backlight: setting(0.0, 0.0),
+        rounding: Rounding::default(),
+        settle: Duration::ZERO,
+        slider_moved: None,
+        commit: Commit::Continuous,
+        reconcile: Reconcile::Rebase,
+        held: false,
+        raw_brightness: false,
+        link: Link::default(),
+        strength: Strength::default(),
+        daemon,
+        worker,
+        deltas_in_flight: None,
+        deltas_sent: Deltas::default(),
+        deltas_unapplied: Deltas::default(),
+        resync_pending: None,
+    };
+    let shared = Shared {
+        settings: Rc::new(RefCell::new(settings)),
+        config: Rc::new(RefCell::new(Config::default())),
+        ticker: Rc::new(Ticker {
+            timer: slint::Timer::default(),
+            interval: Duration::from_millis(7),
+            idle: Cell::new(false),
+        }),
+        night_saved: Rc::default(),
+        override_saved: Rc::default(),
+        history: Rc::default(),
+        schedule_applied: Rc::default(),
+        timer: Rc::default(),
+        guarded: Rc::default(),
+        guard_timer: Rc::default(),
+        safety_revert: None,
+        ddc_displays: Arc::default(),
+        idle_inhibit: None,
+        window_state: Rc::default(),
+        window: Rc::default(),
+        current: Arc::new(Mutex::new(app.as_weak())),
+        focused_output: false,
+        daemonize: false,
+    };
+    connect_window(&app, &shared);
+    Ok((app, shared, mock))
+}
+
+// what the tick timer does with the daemon, with the mock worker answering at once.
+pub fn tick(app: &WlGammaRelayApplet, shared: &Shared, worker: &mut MockWorker) {
+    let mut settings = shared.settings.borrow_mut();
+    sync_worker(app, &mut settings);
+    guard_changes(app, shared, &settings);
+    sync_backlight(app, &mut settings);
+    drop(settings);
+    worker.answer();
+}
+
+// how the pointer moves during a benchmark: slider changes per tick, or none.
+const SCENARIOS: [(&str, usize); 3] = [("idle", 0), ("drag", 1), ("burst", 8)];
+
+// time `ticks` ticks of each scenario against the mock daemon and print the
+// cost per tick and the daemon calls made, e.g. to compare tick and batching
+// changes by. the window is never drawn, so this measures the pipeline alone.
+pub fn run(ticks: u32) -> Result<(), AppletError> {
+    let start = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: 5500,
+        brightness: 0.8,
+        gamma: 1.0,
+    };
+    println!(
+        "{:<8} {:>6} {:>10} {:>10} {:>10} {:>10} {:>11}",
+        "scenario", "ticks", "mean", "p50", "p99", "max", "calls/tick"
+    );
+    for (name, changes) in SCENARIOS {
+        let (app, shared, mut worker) = headless_applet(start)?;
+        let mut times = Vec::with_capacity(ticks as usize);
+        let mut calls = 0;
+        let mut step = 0u32;
+        for _ in 0..ticks {
+            let began = Instant::now();
+            // sweep temperature and brightness up and down the middle of their
+            // sliders, as a drag or a fast scroll does.
+            for _ in 0..changes {
+                step += 1;
+                let phase = (step % 200) as f32 / 100.0;
+                let value = 0.3 + 0.4 * if phase > 1.0 { 2.0 - phase } else { phase };
+                let parameters = app.global::<Parameters>();
+                parameters.invoke_slider_changed("temperature".into(), value);
+                parameters.invoke_slider_changed("brightness".into(), value + 0.2);
+            }
+            tick(&app, &shared, &mut worker);
+            times.push(began.elapsed());
+            calls += worker.calls().len();
+        }
+        times.sort();
+        let micros = |time: Duration| time.as_secs_f64() * 1e6;
+        let shown = |micros: f64| format!("{micros:.1} µs");
+        let percentile = |p: usize| shown(micros(times[(times.len() - 1) * p / 100]));
+        let mean = times.iter().map(|time| micros(*time)).sum::<f64>() / times.len() as f64;
+        println!(
+            "{name:<8} {ticks:>6} {:>10} {:>10} {:>10} {:>10} {:>11.2}",
+            shown(mean),
+            percentile(50),
+            percentile(99),
+            percentile(100),
+            calls as f64 / ticks as f64
+        );
+    }
+    Ok(())
+}
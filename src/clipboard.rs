@@ -0,0 +1,79 @@
+use std::{io::Write, process::Stdio};
+
+use crate::{config::Preset, daemon::DAEMON_SERVICE, sandbox, worker::DaemonState, AppletError};
+
+// which values a snippet carries: those of the shown controls.
+pub struct Fields {
+    pub temperature: bool,
+    pub brightness: bool,
+    pub gamma: bool,
+    pub invert: bool,
+}
+
+// busctl commands that set the daemon to `state` again, e.g. from a script.
+pub fn busctl_snippet(state: &DaemonState, fields: &Fields, output: Option<&str>) -> String {
+    let path = match output {
+        Some(output) => format!("/outputs/{output}"),
+        None => "/".to_string(),
+    };
+    let properties = [
+        (
+            fields.temperature,
+            "Temperature q",
+            state.temperature.to_string(),
+        ),
+        (
+            fields.brightness,
+            "Brightness d",
+            format!("{:.2}", state.brightness),
+        ),
+        (fields.gamma, "Gamma d", format!("{:.2}", state.gamma)),
+        (fields.invert, "Inverted b", state.inverted.to_string()),
+    ];
+    properties
+        .into_iter()
+        .filter(|(shown, ..)| *shown)
+        .map(|(_, property, value)| {
+            format!(
+                "busctl --user set-property {DAEMON_SERVICE} {path} rs.wl.gammarelay {property} {value}\n"
+            )
+        })
+        .collect()
+}
+
+// a preset for the config file with the values of `state`.
+pub fn preset_snippet(state: &DaemonState, fields: &Fields) -> Result<String, AppletError> {
+    let preset = Preset {
+        temperature: fields.temperature.then_some(state.temperature),
+        brightness: fields
+            .brightness
+            .then_some((state.brightness * 100.0).round() / 100.0),
+        gamma: fields
+            .gamma
+            .then_some((state.gamma * 100.0).round() / 100.0),
+        inverted: fields.invert.then_some(state.inverted),
+    };
+    Ok(format!("[presets.copied]\n{}", toml::to_string(&preset)?))
+}
+
+// put text on the wayland clipboard with wl-copy, which keeps serving it after
+// the applet exits.
+pub fn copy(text: &str) -> Result<(), AppletError> {
+    let mut child = sandbox::host_command("wl-copy")
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppletError::Clipboard(format!("wl-copy: {e}; install wl-clipboard")))?;
+    child
+        .stdin
+        .take()
+        .expect("rust: wl-copy stdin")
+        .write_all(text.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(AppletError::Clipboard(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
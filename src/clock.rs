@@ -0,0 +1,19 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// minutes since local midnight, including the part of the current minute.
+// localtime_r follows TZ and /etc/localtime, and is safe to call from any thread.
+pub fn local_minute() -> f64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let seconds = now.as_secs() as libc::time_t;
+    // SAFETY: tm is plain data, and localtime_r only writes to the tm it is given.
+    let tm = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&seconds, &mut tm);
+        tm
+    };
+    tm.tm_hour as f64 * 60.0
+        + tm.tm_min as f64
+        + (tm.tm_sec as f64 + now.subsec_millis() as f64 / 1000.0) / 60.0
+}
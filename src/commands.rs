@@ -0,0 +1,656 @@
+use std::path::{Path, PathBuf};
+
+use zbus::{
+    blocking::fdo::{DBusProxy, PropertiesProxy},
+    blocking::Connection,
+    names::WellKnownName,
+};
+
+use crate::{
+    config::{default_config_path, Config, Preset},
+    daemon::{Capabilities, DAEMON_SERVICE},
+    ipc::{self, APPLET_SERVICE},
+    pending::Op,
+    sandbox::{self, Sandbox, SESSION_NAMES, SYSTEM_NAMES},
+    worker::{write_value, DaemonState},
+    AppletError, GammaRelayProxyBlocking,
+};
+
+// print the config as a file would set it, so it can be saved as one. flags are
+// not part of the file, so they follow as comments.
+pub fn config_dump(config: &Config, flags: &impl serde::Serialize) -> Result<(), AppletError> {
+    match &config.path {
+        Some(path) if path.exists() => println!("# {}", path.display()),
+        Some(path) => println!("# defaults; no file at {}", path.display()),
+        None => println!("# defaults"),
+    }
+    print!("{}", toml::to_string(config)?);
+    println!("\n# flags");
+    for line in toml::to_string(flags)?.lines() {
+        println!("# {line}");
+    }
+    Ok(())
+}
+
+// print current daemon values and what the daemon supports, one `key: value` per line.
+pub fn status(proxy: &GammaRelayProxyBlocking) -> Result<(), AppletError> {
+    let capabilities = Capabilities::detect(proxy)?;
+    println!("daemon version: {}", capabilities.version_string());
+    if capabilities.supports_control("temperature") {
+        println!("temperature: {}", proxy.temperature()?);
+    }
+    if capabilities.supports_control("brightness") {
+        println!("brightness: {:.2}", proxy.brightness()?);
+    }
+    if capabilities.supports_control("gamma") {
+        println!("gamma: {:.2}", proxy.gamma()?);
+    }
+    if capabilities.supports_control("invert") {
+        println!("inverted: {}", proxy.inverted()?);
+    }
+    match &capabilities.outputs {
+        Some(outputs) => println!("outputs: {}", outputs.join(" ")),
+        None => println!("outputs: unsupported"),
+    }
+    Ok(())
+}
+
+// list the outputs the daemon controls one by one, with their values, in the
+// names --output takes. without per-output control, list the compositor's
+// outputs for reference.
+pub fn outputs(proxy: &GammaRelayProxyBlocking) -> Result<(), AppletError> {
+    let capabilities = Capabilities::detect(proxy)?;
+    let Some(outputs) = &capabilities.outputs else {
+        eprintln!(
+            "daemon (version {}) does not support per-output control; compositor outputs:",
+            capabilities.version_string()
+        );
+        for name in crate::wayland::output_names()? {
+            println!("{name}");
+        }
+        return Ok(());
+    };
+    for output in outputs {
+        let state = read_state(&crate::create_proxy(Some(output))?, &capabilities)?;
+        println!(
+            "{output} temperature={} brightness={:.2} gamma={:.2} inverted={}",
+            state.temperature, state.brightness, state.gamma, state.inverted
+        );
+    }
+    Ok(())
+}
+
+// print the values the applet left the daemon at as shell assignments, for
+// wrapper scripts to `eval`.
+pub fn print_values(state: &DaemonState, output: Option<&str>) {
+    if let Some(output) = output {
+        println!("OUTPUT={output}");
+    }
+    println!(
+        "{}",
+        format_state(
+            "TEMPERATURE={temperature}\nBRIGHTNESS={brightness}\nGAMMA={gamma}\nINVERTED={inverted}",
+            state
+        )
+    );
+}
+
+// read the daemon values, taking what the daemon lacks as neutral.
+fn read_state(
+    proxy: &GammaRelayProxyBlocking,
+    capabilities: &Capabilities,
+) -> zbus::Result<DaemonState> {
+    Ok(DaemonState {
+        inverted: capabilities.supports_control("invert") && proxy.inverted()?,
+        grayscale: capabilities.supports_control("grayscale") && proxy.grayscale()?,
+        temperature: proxy.temperature()?,
+        brightness: proxy.brightness()?,
+        gamma: if capabilities.supports_control("gamma") {
+            proxy.gamma()?
+        } else {
+            1.0
+        },
+    })
+}
+
+// fill in the `{name}` placeholders of a watch format. unknown ones are left as is.
+fn format_state(format: &str, state: &DaemonState) -> String {
+    format
+        .replace("{temperature}", &state.temperature.to_string())
+        .replace("{brightness}", &format!("{:.2}", state.brightness))
+        .replace(
+            "{brightness_pct}",
+            &format!("{:.0}", state.brightness * 100.0),
+        )
+        .replace("{gamma}", &format!("{:.2}", state.gamma))
+        .replace("{inverted}", &state.inverted.to_string())
+}
+
+// how watch lines are wrapped for the status bar reading them.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormatMode {
+    // the formatted text as is.
+    #[default]
+    Plain,
+    // json for an i3status-rust `custom` block with `json = true`.
+    I3status,
+    // text with polybar action tags for a `custom/script` module with `tail = true`.
+    Polybar,
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// polybar ends an action's command at the next unescaped colon.
+fn polybar_action(button: u8, command: &str, text: &str) -> String {
+    format!(
+        "%{{A{button}:{}:}}{text}%{{A}}",
+        command.replace(':', "\\:")
+    )
+}
+
+impl FormatMode {
+    fn line(self, text: String, state: &DaemonState, output: Option<&str>) -> String {
+        match self {
+            Self::Plain => text,
+            // the block is highlighted while the screen is shifted from neutral.
+            Self::I3status => {
+                let shifted = state.inverted
+                    || state.temperature != 6500
+                    || state.brightness < 1.0
+                    || (state.gamma - 1.0).abs() > 0.005;
+                format!(
+                    "{{\"state\": \"{}\", \"text\": {}}}",
+                    if shifted { "Info" } else { "Idle" },
+                    json_string(&text)
+                )
+            }
+            // click opens the applet, right click cycles presets, scroll shifts temperature.
+            Self::Polybar => {
+                let mut exe =
+                    sandbox::launch_command().unwrap_or("wl-gammarelay-applet".to_string());
+                let path = match output {
+                    Some(output) => {
+                        exe.push_str(&format!(" --output {output}"));
+                        format!("/outputs/{output}")
+                    }
+                    None => "/".to_string(),
+                };
+                let update = |delta: &str| {
+                    format!("busctl --user -- call {DAEMON_SERVICE} {path} rs.wl.gammarelay UpdateTemperature n {delta}")
+                };
+                let text = polybar_action(1, &exe, &text);
+                let text = polybar_action(3, &format!("{exe} --cycle-preset"), &text);
+                let text = polybar_action(4, &update("+100"), &text);
+                polybar_action(5, &update("-100"), &text)
+            }
+        }
+    }
+}
+
+// print a formatted line now and each time the daemon values change, for status
+// bars and scripts. runs until the daemon's connection closes.
+pub fn watch(
+    proxy: &GammaRelayProxyBlocking,
+    format: &str,
+    mode: FormatMode,
+    output: Option<&str>,
+) -> Result<(), AppletError> {
+    follow(proxy, |_, state| {
+        println!("{}", mode.line(format_state(format, state), state, output));
+    })
+}
+
+// call `changed` with the daemon values now and each time they change, along
+// with the values before, if any. runs until the daemon's connection closes.
+pub fn follow(
+    proxy: &GammaRelayProxyBlocking,
+    mut changed: impl FnMut(Option<&DaemonState>, &DaemonState),
+) -> Result<(), AppletError> {
+    let capabilities = Capabilities::detect(proxy)?;
+    let properties = PropertiesProxy::builder(proxy.inner().connection())
+        .destination(DAEMON_SERVICE)?
+        .path(proxy.inner().path().to_owned())?
+        .build()?;
+    // subscribe before the first read, so no change falls in between.
+    let changes = properties.receive_properties_changed()?;
+    let mut last = read_state(proxy, &capabilities)?;
+    changed(None, &last);
+    for _ in changes {
+        let state = read_state(proxy, &capabilities)?;
+        // one change can arrive as several signals; report each state once.
+        if state != last {
+            changed(Some(&last), &state);
+            last = state;
+        }
+    }
+    Ok(())
+}
+
+// apply the preset after the one the daemon shows. a running applet is asked to
+// do it, so its sliders follow; otherwise the daemon is written directly.
+pub fn cycle_preset(
+    proxy: &GammaRelayProxyBlocking,
+    config: &Config,
+    output: Option<&str>,
+) -> Result<(), AppletError> {
+    if ipc::call_running(proxy.inner().connection(), output, "CyclePreset", &())? {
+        return Ok(());
+    }
+
+    let capabilities = Capabilities::detect(proxy)?;
+    let mut state = read_state(proxy, &capabilities)?;
+    let Some((name, preset)) = config.next_preset(&state) else {
+        return Err(AppletError::InvalidConfig(
+            "no presets to cycle".to_string(),
+        ));
+    };
+    write_preset(proxy, &mut state, preset, |control| {
+        capabilities.supports_control(control)
+    })?;
+    println!("{name}");
+    Ok(())
+}
+
+// switch gamma between the two [gamma_toggle] values, through the running
+// applet if there is one, and print the new value.
+pub fn toggle_gamma(
+    proxy: &GammaRelayProxyBlocking,
+    config: &Config,
+    output: Option<&str>,
+) -> Result<(), AppletError> {
+    if ipc::call_running(proxy.inner().connection(), output, "ToggleGamma", &())? {
+        return Ok(());
+    }
+
+    let capabilities = Capabilities::detect(proxy)?;
+    if !capabilities.supports_control("gamma") {
+        return Err(AppletError::Unsupported("gamma"));
+    }
+    let mut state = read_state(proxy, &capabilities)?;
+    let gamma = config.gamma_toggle.next(state.gamma);
+    write_value(proxy, &mut state, Op::Gamma(gamma))?;
+    println!("{gamma:.2}");
+    Ok(())
+}
+
+fn write_preset(
+    proxy: &GammaRelayProxyBlocking,
+    state: &mut DaemonState,
+    preset: Preset,
+    supports: impl Fn(&str) -> bool,
+) -> zbus::Result<()> {
+    if let Some(gamma) = preset.gamma.filter(|_| supports("gamma")) {
+        write_value(proxy, state, Op::Gamma(gamma))?;
+    }
+    if let Some(brightness) = preset.brightness {
+        write_value(proxy, state, Op::Brightness(brightness))?;
+    }
+    if let Some(temperature) = preset.temperature {
+        write_value(proxy, state, Op::Temperature(temperature))?;
+    }
+    if let Some(inverted) = preset.inverted.filter(|_| supports("invert")) {
+        write_value(proxy, state, Op::Invert(inverted))?;
+    }
+    Ok(())
+}
+
+// write the values of an output's profile to it. `if_neutral` only writes an
+// output still at the daemon's defaults, so values set since are kept.
+// outputs only exist on daemons that have gamma.
+pub fn apply_output_profile(
+    output: &str,
+    values: Preset,
+    if_neutral: bool,
+) -> Result<(), AppletError> {
+    let proxy = crate::create_proxy(Some(output))?;
+    let mut state = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: proxy.temperature()?,
+        brightness: proxy.brightness()?,
+        gamma: proxy.gamma()?,
+    };
+    if if_neutral && !Preset::NEUTRAL.matches(&state) {
+        return Ok(());
+    }
+    Ok(write_preset(&proxy, &mut state, values, |_| true)?)
+}
+
+const SYSTEMD_UNIT: &str = "wl-gammarelay-applet.service";
+
+// where install-service writes: the DBus activation file, and the systemd unit.
+fn service_paths() -> Result<(PathBuf, PathBuf), AppletError> {
+    let missing = || AppletError::InvalidConfig("neither XDG_* nor HOME is set".to_string());
+    let data_home = sandbox::host_data_home().ok_or_else(missing)?;
+    let config_home = sandbox::host_config_home().ok_or_else(missing)?;
+    Ok((
+        data_home
+            .join("dbus-1/services")
+            .join(format!("{APPLET_SERVICE}.service")),
+        config_home.join("systemd/user").join(SYSTEMD_UNIT),
+    ))
+}
+
+// write a DBus activation file, so calling the applet's interface (e.g. Toggle
+// from a key binding) starts a background instance when none is running. with
+// `systemd`, the activation goes through a user unit, optionally ordered after
+// the daemon's unit.
+pub fn install_service(systemd: bool, after: Option<&str>) -> Result<(), AppletError> {
+    let (activation_path, unit_path) = service_paths()?;
+    let exe = sandbox::launch_command()?;
+    let mut activation =
+        format!("[D-BUS Service]\nName={APPLET_SERVICE}\nExec={exe} --daemonize\n");
+    if systemd {
+        activation.push_str(&format!("SystemdService={SYSTEMD_UNIT}\n"));
+        let ordering = after
+            .map(|unit| format!("After={unit}\nWants={unit}\n"))
+            .unwrap_or_default();
+        // flatpak run does not pass the notify socket into the sandbox.
+        let kind = match sandbox::current() {
+            Some(_) => "exec",
+            None => "notify",
+        };
+        write_file(
+            &unit_path,
+            &format!(
+                "[Unit]\n\
+                 Description=Control wl-gammarelay-rs via applet\n\
+                 PartOf=graphical-session.target\n\
+                 After=graphical-session.target\n\
+                 {ordering}\n\
+                 [Service]\n\
+                 Type={kind}\n\
+                 BusName={APPLET_SERVICE}\n\
+                 ExecStart={exe} --daemonize\n\
+                 Restart=on-failure\n\n\
+                 [Install]\n\
+                 WantedBy=graphical-session.target\n"
+            ),
+        )?;
+    }
+    write_file(&activation_path, &activation)?;
+    if systemd {
+        println!(
+            "run: systemctl --user daemon-reload && systemctl --user enable --now {SYSTEMD_UNIT}"
+        );
+    }
+    Ok(())
+}
+
+// remove the activation file and the systemd unit, where they exist.
+pub fn uninstall_service() -> Result<(), AppletError> {
+    let (activation_path, unit_path) = service_paths()?;
+    let mut unit_removed = false;
+    for path in [&activation_path, &unit_path] {
+        match std::fs::remove_file(path) {
+            Ok(()) => {
+                println!("removed {}", path.display());
+                unit_removed |= path == &unit_path;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if unit_removed {
+        println!("run: systemctl --user disable {SYSTEMD_UNIT}; systemctl --user daemon-reload");
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, contents: &str) -> Result<(), AppletError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+enum Check {
+    Ok,
+    Warn,
+    Fail,
+}
+
+// print one diagnostic line; problems carry a hint on how to fix them.
+fn report(check: Check, subject: &str, detail: &str) -> bool {
+    let tag = match check {
+        Check::Ok => " ok ",
+        Check::Warn => "warn",
+        Check::Fail => "FAIL",
+    };
+    println!("[{tag}] {subject}: {detail}");
+    !matches!(check, Check::Fail)
+}
+
+// check the environment the applet depends on and print actionable results.
+// returns an error if any required piece is missing.
+pub fn doctor(config_path: Option<&Path>) -> Result<(), AppletError> {
+    let mut healthy = true;
+
+    healthy &= match Config::load(config_path.map(Path::to_path_buf)) {
+        Ok(_) => {
+            let detail = match config_path
+                .map(Path::to_path_buf)
+                .or_else(default_config_path)
+            {
+                Some(path) if path.exists() => path.display().to_string(),
+                Some(path) => format!("no file at {}, using defaults", path.display()),
+                None => "no config location, using defaults".to_string(),
+            };
+            report(Check::Ok, "config", &detail)
+        }
+        Err(e) => report(
+            Check::Fail,
+            "config",
+            &format!("{e}; fix or remove the file"),
+        ),
+    };
+
+    healthy &= match (
+        std::env::var("WAYLAND_DISPLAY"),
+        crate::wayland::global_interfaces(),
+    ) {
+        (Ok(display), Ok(globals)) => {
+            report(Check::Ok, "wayland", &format!("connected to {display}"));
+            let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or("unknown".into());
+            report(Check::Ok, "compositor", &desktop);
+            if globals.iter().any(|g| g == "zwlr_gamma_control_manager_v1") {
+                report(
+                    Check::Ok,
+                    "gamma control",
+                    "compositor supports wlr-gamma-control",
+                )
+            } else {
+                report(
+                    Check::Fail,
+                    "gamma control",
+                    "compositor lacks wlr-gamma-control; wl-gammarelay-rs cannot work here",
+                )
+            }
+        }
+        (Err(_), _) => report(
+            Check::Fail,
+            "wayland",
+            "WAYLAND_DISPLAY is not set; run from inside a wayland session",
+        ),
+        (Ok(display), Err(e)) => report(
+            Check::Fail,
+            "wayland",
+            &format!("cannot connect to {display}: {e}"),
+        ),
+    };
+
+    let connection = match Connection::session() {
+        Ok(connection) => {
+            report(Check::Ok, "session bus", "connected");
+            connection
+        }
+        Err(e) => {
+            report(
+                Check::Fail,
+                "session bus",
+                &format!("{e}; is DBUS_SESSION_BUS_ADDRESS set for this session?"),
+            );
+            return Err(AppletError::Unhealthy);
+        }
+    };
+
+    let dbus = DBusProxy::new(&connection)?;
+    let service = WellKnownName::from_static_str(DAEMON_SERVICE).map_err(zbus::Error::from)?;
+    let running = dbus.name_has_owner(service.clone().into())?;
+    let activatable = dbus
+        .list_activatable_names()?
+        .iter()
+        .any(|name| name.as_str() == DAEMON_SERVICE);
+    healthy &= if running {
+        let proxy = GammaRelayProxyBlocking::new(&connection)?;
+        let capabilities = Capabilities::detect(&proxy)?;
+        report(
+            Check::Ok,
+            "daemon",
+            &format!(
+                "{DAEMON_SERVICE} is running (version {})",
+                capabilities.version_string()
+            ),
+        );
+        for control in ["temperature", "brightness", "gamma", "invert"] {
+            if !capabilities.supports_control(control) {
+                report(
+                    Check::Warn,
+                    "daemon",
+                    &format!("{control} is unsupported; update wl-gammarelay-rs"),
+                );
+            }
+        }
+        true
+    } else if activatable {
+        report(
+            Check::Ok,
+            "daemon",
+            &format!("{DAEMON_SERVICE} is not running, but is dbus-activatable"),
+        )
+    } else {
+        report(
+            Check::Fail,
+            "daemon",
+            &format!(
+                "{DAEMON_SERVICE} is not running; start `wl-gammarelay-rs run` from your compositor config"
+            ),
+        )
+    };
+
+    match sandbox::find_program("wl-gammarelay-rs") {
+        Some(path) => report(Check::Ok, "wl-gammarelay-rs", &path.display().to_string()),
+        None => report(
+            Check::Warn,
+            "wl-gammarelay-rs",
+            &format!(
+                "not found {}; fine if it is started another way",
+                program_location()
+            ),
+        ),
+    };
+
+    match sandbox::find_program("ddcutil") {
+        Some(path) => report(Check::Ok, "ddcutil", &path.display().to_string()),
+        None => report(
+            Check::Warn,
+            "ddcutil",
+            &format!("not found {}; needed for --ddc", program_location()),
+        ),
+    };
+
+    match std::fs::read_dir("/sys/class/backlight").map(|entries| entries.count()) {
+        Ok(count) if count > 0 => report(Check::Ok, "backlight", "device found for --backlight"),
+        _ => report(
+            Check::Warn,
+            "backlight",
+            "no device; --backlight slider stays hidden",
+        ),
+    };
+
+    if let Some(sandbox) = sandbox::current() {
+        healthy &= report_sandbox(sandbox);
+    }
+
+    if healthy {
+        Ok(())
+    } else {
+        Err(AppletError::Unhealthy)
+    }
+}
+
+// where find_program looks, for hints.
+fn program_location() -> &'static str {
+    if sandbox::current().is_some() {
+        "on the host"
+    } else {
+        "on PATH"
+    }
+}
+
+// check the bus names a sandbox lets the applet reach, naming the flatpak
+// permission that grants each one that is missing.
+fn report_sandbox(sandbox: &Sandbox) -> bool {
+    match &sandbox.app_id {
+        Some(app_id) => report(Check::Ok, "sandbox", &format!("flatpak {app_id}")),
+        None => report(
+            Check::Ok,
+            "sandbox",
+            "--sandbox; host tools run through flatpak-spawn --host",
+        ),
+    };
+    if !sandbox.knows_permissions() {
+        report(
+            Check::Warn,
+            "sandbox",
+            "permissions unknown outside flatpak; a flatpak needs --socket=wayland and the names below",
+        );
+    }
+    let mut healthy = true;
+    let names = SESSION_NAMES
+        .iter()
+        .map(|name| (false, name))
+        .chain(SYSTEM_NAMES.iter().map(|name| (true, name)));
+    for (system, (name, access, purpose)) in names {
+        let flag = match (system, *access) {
+            (true, _) => format!("--system-talk-name={name}"),
+            (false, "own") => format!("--own-name={name}"),
+            (false, _) => format!("--talk-name={name}"),
+        };
+        let subject = format!("sandbox {name}");
+        healthy &= if !sandbox.knows_permissions() {
+            report(Check::Ok, &subject, &format!("{flag} for {purpose}"))
+        } else if sandbox.allows(system, name, access) {
+            report(Check::Ok, &subject, &format!("granted for {purpose}"))
+        } else if *name == DAEMON_SERVICE {
+            report(
+                Check::Fail,
+                &subject,
+                &format!("grant {flag} for {purpose}"),
+            )
+        } else {
+            report(
+                Check::Warn,
+                &subject,
+                &format!("grant {flag} for {purpose}"),
+            )
+        };
+    }
+    healthy
+}
@@ -0,0 +1,115 @@
+use std::{thread::sleep, time::Duration};
+
+use crate::{sandbox, AppletError};
+
+// a newly started window takes a moment to appear in the compositor's tree.
+const MOVE_ATTEMPTS: u32 = 20;
+const MOVE_RETRY: Duration = Duration::from_millis(100);
+
+// compositors whose IPC can move a window to another output, found through
+// the environment variables they set.
+enum Compositor {
+    Sway,
+    Hyprland,
+}
+
+impl Compositor {
+    fn detect() -> Option<Self> {
+        if std::env::var_os("SWAYSOCK").is_some() {
+            Some(Self::Sway)
+        } else if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Self::Hyprland)
+        } else {
+            None
+        }
+    }
+}
+
+fn ipc(program: &str, args: &[&str]) -> Result<String, AppletError> {
+    let output = sandbox::host_command(program).args(args).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    // swaymsg reports failed commands in its json reply, and hyprctl dispatch
+    // replies "ok" only on success.
+    let failed = stdout.contains("\"success\": false")
+        || (args.first() == Some(&"dispatch") && stdout.trim() != "ok");
+    if !output.status.success() || failed {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AppletError::Compositor(
+            format!("{program}: {} {}", stdout.trim(), stderr.trim())
+                .trim()
+                .to_string(),
+        ));
+    }
+    Ok(stdout)
+}
+
+// parse `swaymsg -t get_outputs`, e.g. "Output DP-1 'Dell Inc. U2720Q' (focused)".
+fn parse_sway_focused(text: &str) -> Option<String> {
+    text.lines()
+        .filter(|line| line.ends_with("(focused)"))
+        .find_map(|line| line.strip_prefix("Output "))
+        .and_then(|line| line.split_whitespace().next())
+        .map(str::to_string)
+}
+
+// parse `hyprctl monitors` for the focused monitor's active workspace id. each
+// monitor starts with "Monitor DP-1 (ID 0):" followed by indented fields such as
+// "active workspace: 3 (3)" and "focused: yes".
+fn parse_hyprland_focused(text: &str) -> Option<String> {
+    let mut workspace = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("Monitor ") {
+            workspace = None;
+        } else if let Some(value) = line.strip_prefix("active workspace: ") {
+            workspace = value.split_whitespace().next().map(str::to_string);
+        } else if line == "focused: yes" {
+            return workspace;
+        }
+    }
+    None
+}
+
+// move this process's window to the output that has focus, so the applet opens
+// where the user is looking. does nothing outside sway and hyprland.
+pub fn move_to_focused_output() -> Result<(), AppletError> {
+    let Some(compositor) = Compositor::detect() else {
+        return Ok(());
+    };
+    let pid = std::process::id();
+    let (program, args) = match compositor {
+        Compositor::Sway => {
+            let Some(output) = parse_sway_focused(&ipc("swaymsg", &["-t", "get_outputs"])?) else {
+                return Ok(());
+            };
+            (
+                "swaymsg",
+                vec![format!("[pid={pid}] move container to output {output}")],
+            )
+        }
+        Compositor::Hyprland => {
+            let Some(workspace) = parse_hyprland_focused(&ipc("hyprctl", &["monitors"])?) else {
+                return Ok(());
+            };
+            (
+                "hyprctl",
+                vec![
+                    "dispatch".to_string(),
+                    "movetoworkspacesilent".to_string(),
+                    format!("{workspace},pid:{pid}"),
+                ],
+            )
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut attempt = 1;
+    loop {
+        match ipc(program, &args) {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= MOVE_ATTEMPTS => return Err(e),
+            Err(_) => {
+                attempt += 1;
+                sleep(MOVE_RETRY);
+            }
+        }
+    }
+}
@@ -0,0 +1,1123 @@
+use std::{fmt::Display, ops::RangeInclusive, path::PathBuf};
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    theme::{Mode, Source},
+    toplevel::Toplevel,
+    worker::DaemonState,
+    AppletError,
+};
+
+// values the daemon and the sliders accept.
+pub const TEMPERATURE_RANGE: RangeInclusive<u16> = 1000..=10000;
+pub const BRIGHTNESS_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+pub const GAMMA_RANGE: RangeInclusive<f64> = 0.5..=1.5;
+
+// the preset game mode applies, built in unless the config file defines it.
+pub const NEUTRAL_PRESET: &str = "neutral";
+
+pub const CONTROL_NAMES: [&str; 7] = [
+    "invert",
+    "strength",
+    "temperature",
+    "brightness",
+    "gamma",
+    "backlight",
+    "ddc",
+];
+
+// optional file at $XDG_CONFIG_HOME/wl-gammarelay-applet/config.toml
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Order of control widgets, e.g. `["brightness", "temperature", "invert", "gamma"]`
+    pub order: Vec<String>,
+    /// Named sets of values, in the order they appear in the file
+    pub presets: IndexMap<String, Preset>,
+    /// Preset applied by the night mode switch, which is shown if the preset exists
+    pub night_preset: String,
+    /// Whether presets that set `inverted` switch color inversion
+    pub presets_invert: bool,
+    /// Amounts changed by the step buttons
+    pub step: Steps,
+    /// Rounding of applied and shown temperatures
+    pub rounding: Rounding,
+    /// Gathering of slider moves into fewer writes
+    pub smoothing: Smoothing,
+    /// Curve along which brightness follows temperature
+    pub link: Link,
+    /// Endpoints of the night light strength slider
+    pub strength: Strength,
+    /// Two gamma values to switch between
+    pub gamma_toggle: GammaToggle,
+    /// Actions of mouse buttons and the wheel on the window background
+    pub buttons: Buttons,
+    /// Presets applied when the desktop switches between light and dark mode
+    pub theme: Theme,
+    /// Neutral values while a fullscreen window is shown
+    pub game_mode: GameMode,
+    /// Presets applied while a given application is focused or fullscreen
+    pub rules: Vec<Rule>,
+    /// Brightness following the screen content (experimental)
+    pub auto_brightness: AutoBrightness,
+    /// Day and night presets following the clock
+    pub schedule: Schedule,
+    /// Preset applied for a while by the timer button
+    pub timer: Timer,
+    /// Values and presets of single monitors, by output name
+    pub output: IndexMap<String, OutputProfile>,
+    /// Confirmation of values that make the screen hard to read
+    pub guard: Guard,
+    /// File the config was read from, or would be if it existed
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Steps {
+    pub temperature: u16,
+    pub brightness: f64,
+    pub gamma: f64,
+    pub backlight: f64,
+    pub ddc: f64,
+    pub strength: f64,
+}
+
+impl Default for Steps {
+    fn default() -> Self {
+        Self {
+            temperature: 100,
+            brightness: 0.01,
+            gamma: 0.01,
+            backlight: 0.05,
+            ddc: 0.05,
+            strength: 0.05,
+        }
+    }
+}
+
+// temperatures in K. slider changes are applied to the daemon in multiples of
+// `temperature`; the shown value is rounded to `temperature_display`.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rounding {
+    pub temperature: u16,
+    pub temperature_display: u16,
+}
+
+impl Default for Rounding {
+    fn default() -> Self {
+        Self {
+            temperature: 100,
+            temperature_display: 1,
+        }
+    }
+}
+
+// slider moves are gathered until the slider has been still for `settle_ms`,
+// then written as one change, so a fast drag does not step through every value
+// on the way. 0 writes them as they come.
+#[derive(Deserialize, Serialize, Default, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Smoothing {
+    pub settle_ms: u64,
+}
+
+// brightness follows temperature along a curve through `points` of
+// (temperature, brightness), sorted by temperature on load.
+#[derive(Deserialize, Serialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Link {
+    pub enabled: bool,
+    pub points: Vec<(u16, f64)>,
+}
+
+impl Link {
+    // interpolate linearly between points, holding the end values beyond them.
+    pub fn brightness_at(&self, temperature: u16) -> Option<f64> {
+        let first = self.points.first()?;
+        let last = self.points.last()?;
+        if temperature <= first.0 {
+            return Some(first.1);
+        }
+        if temperature >= last.0 {
+            return Some(last.1);
+        }
+        let upper = self.points.iter().position(|p| p.0 >= temperature)?;
+        let (t0, b0) = self.points[upper - 1];
+        let (t1, b1) = self.points[upper];
+        Some(b0 + (b1 - b0) * (temperature - t0) as f64 / (t1 - t0) as f64)
+    }
+}
+
+// the strength slider moves temperature and brightness together, from the first
+// value of each pair at 0% to the second at 100%.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Strength {
+    pub temperature: (u16, u16),
+    pub brightness: (f64, f64),
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Self {
+            temperature: (6500, 3000),
+            brightness: (1.0, 0.8),
+        }
+    }
+}
+
+impl Strength {
+    pub fn values_at(&self, strength: f64) -> (u16, f64) {
+        let strength = strength.clamp(0.0, 1.0);
+        let (t0, t1) = (self.temperature.0 as f64, self.temperature.1 as f64);
+        let (b0, b1) = self.brightness;
+        (
+            (t0 + (t1 - t0) * strength).round() as u16,
+            b0 + (b1 - b0) * strength,
+        )
+    }
+
+    // the strength a temperature corresponds to, clamped to the endpoints.
+    pub fn at_temperature(&self, temperature: u16) -> f64 {
+        let (t0, t1) = (self.temperature.0 as f64, self.temperature.1 as f64);
+        if t0 == t1 {
+            return 0.0;
+        }
+        ((temperature as f64 - t0) / (t1 - t0)).clamp(0.0, 1.0)
+    }
+}
+
+// the γ button, ToggleGamma and --toggle-gamma switch gamma between two values,
+// e.g. one for dark games and one for the desktop.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct GammaToggle {
+    pub button: bool,
+    pub values: (f64, f64),
+}
+
+impl Default for GammaToggle {
+    fn default() -> Self {
+        Self {
+            button: false,
+            values: (1.0, 0.85),
+        }
+    }
+}
+
+impl GammaToggle {
+    // the second value if gamma is at the first, or else the first.
+    pub fn next(&self, gamma: f64) -> f64 {
+        if (gamma - self.values.0).abs() < 0.005 {
+            self.values.1
+        } else {
+            self.values.0
+        }
+    }
+}
+
+// what a mouse button or the wheel does on the window background, away from
+// the sliders and buttons, e.g. right = "reset" or scroll_up = "temperature+".
+#[derive(Deserialize, Serialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Buttons {
+    pub right: Option<String>,
+    pub middle: Option<String>,
+    pub other: Option<String>,
+    pub scroll_up: Option<String>,
+    pub scroll_down: Option<String>,
+}
+
+impl Buttons {
+    fn bound(&self) -> [(&'static str, &Option<String>); 5] {
+        [
+            ("right", &self.right),
+            ("middle", &self.middle),
+            ("other", &self.other),
+            ("scroll_up", &self.scroll_up),
+            ("scroll_down", &self.scroll_down),
+        ]
+    }
+
+    // the action bound to a button by the name the window reports it by.
+    pub fn action(&self, button: &str) -> Option<ButtonAction> {
+        let (_, action) = self.bound().into_iter().find(|(name, _)| *name == button)?;
+        ButtonAction::parse(action.as_deref()?).ok()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ButtonAction {
+    // every slider back to its default, and the effects off.
+    Reset,
+    Invert,
+    Undo,
+    Redo,
+    CyclePreset,
+    ToggleGamma,
+    Close,
+    // "preset:NAME"
+    Preset(String),
+    // "temperature+", "brightness-" and so on, by one step.
+    Step(&'static str, i32),
+}
+
+impl ButtonAction {
+    pub fn parse(action: &str) -> Result<Self, String> {
+        let step = |control: &str, direction| {
+            [
+                "temperature",
+                "brightness",
+                "gamma",
+                "strength",
+                "backlight",
+            ]
+            .into_iter()
+            .find(|name| *name == control)
+            .map(|name| Self::Step(name, direction))
+        };
+        let parsed = match action {
+            "reset" => Some(Self::Reset),
+            "invert" => Some(Self::Invert),
+            "undo" => Some(Self::Undo),
+            "redo" => Some(Self::Redo),
+            "cycle-preset" => Some(Self::CyclePreset),
+            "toggle-gamma" => Some(Self::ToggleGamma),
+            "close" => Some(Self::Close),
+            _ => {
+                if let Some(name) = action.strip_prefix("preset:") {
+                    Some(Self::Preset(name.to_string()))
+                } else if let Some(control) = action.strip_suffix('+') {
+                    step(control, 1)
+                } else {
+                    action
+                        .strip_suffix('-')
+                        .and_then(|control| step(control, -1))
+                }
+            }
+        };
+        parsed.ok_or_else(|| format!("unknown action \"{action}\""))
+    }
+}
+
+// presets applied for the desktop's light and dark mode, as reported by darkman
+// or by the xdg-desktop-portal color-scheme setting.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub darkman: bool,
+    pub portal: bool,
+    pub light: String,
+    pub dark: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            darkman: false,
+            portal: false,
+            light: "day".to_string(),
+            dark: "night".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    pub fn sources(&self) -> Vec<Source> {
+        [
+            (self.darkman, Source::Darkman),
+            (self.portal, Source::Portal),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, source)| enabled.then_some(source))
+        .collect()
+    }
+
+    pub fn preset_name(&self, mode: Mode) -> &str {
+        match mode {
+            Mode::Light => &self.light,
+            Mode::Dark => &self.dark,
+        }
+    }
+}
+
+// the neutral preset is applied while a window is fullscreen, e.g. a game or a
+// movie. with `app_ids` set, only those applications count.
+#[derive(Deserialize, Serialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameMode {
+    pub enabled: bool,
+    pub app_ids: Vec<String>,
+}
+
+impl GameMode {
+    pub fn active(&self, toplevels: &[Toplevel]) -> bool {
+        toplevels.iter().any(|toplevel| {
+            toplevel.fullscreen
+                && (self.app_ids.is_empty() || self.app_ids.contains(&toplevel.app_id))
+        })
+    }
+}
+
+// experimental: brightness follows the average luma of the screen content, so a
+// bright page is dimmed and a dark one raised toward `target`, the luma times
+// brightness wanted. adjusting starts once the wanted brightness is more than
+// `tolerance` away and stops once it is reached, moving at most `step` per sample.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoBrightness {
+    pub enabled: bool,
+    pub target: f64,
+    pub tolerance: f64,
+    pub step: f64,
+    pub interval_ms: u64,
+    pub min_brightness: f64,
+    pub max_brightness: f64,
+}
+
+impl Default for AutoBrightness {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target: 0.4,
+            tolerance: 0.1,
+            step: 0.03,
+            interval_ms: 1000,
+            min_brightness: 0.5,
+            max_brightness: 1.0,
+        }
+    }
+}
+
+impl AutoBrightness {
+    // the brightness to move to after a sample of `luminance`, if any.
+    pub fn next(&self, brightness: f64, luminance: f64, adjusting: &mut bool) -> Option<f64> {
+        let wanted =
+            (self.target / luminance.max(0.01)).clamp(self.min_brightness, self.max_brightness);
+        let error = wanted - brightness;
+        if error.abs() > self.tolerance {
+            *adjusting = true;
+        } else if error.abs() < 0.01 {
+            *adjusting = false;
+        }
+        if !*adjusting {
+            return None;
+        }
+        Some(brightness + error.clamp(-self.step, self.step))
+    }
+}
+
+// a time of day, written "HH:MM", as minutes since midnight.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub u16);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let (hours, minutes) = text
+            .split_once(':')
+            .and_then(|(h, m)| Some((h.parse::<u16>().ok()?, m.parse::<u16>().ok()?)))
+            .filter(|(h, m)| *h < 24 && *m < 60)
+            .ok_or_else(|| format!("\"{text}\" is not a time of day like \"19:30\""))?;
+        Ok(Self(hours * 60 + minutes))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        format!("{:02}:{:02}", time.0 / 60, time.0 % 60)
+    }
+}
+
+// values for one monitor, in a section named after it, e.g. [output."DP-1"].
+// they are written to it when it is connected, and at startup if it still shows
+// the daemon's defaults. its presets add to or replace the global ones in an
+// applet for that output.
+#[derive(Deserialize, Serialize, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputProfile {
+    pub temperature: Option<u16>,
+    pub brightness: Option<f64>,
+    pub gamma: Option<f64>,
+    pub presets: IndexMap<String, Preset>,
+}
+
+impl OutputProfile {
+    pub fn values(&self) -> Preset {
+        Preset {
+            temperature: self.temperature,
+            brightness: self.brightness,
+            gamma: self.gamma,
+            inverted: None,
+        }
+    }
+}
+
+// the ⏲ button applies `preset` for `minutes`, then restores what it replaced.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timer {
+    pub preset: String,
+    pub minutes: u32,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self {
+            preset: "night".to_string(),
+            minutes: 90,
+        }
+    }
+}
+
+// values set by hand with brightness below `brightness` or a temperature below
+// `temperature` must be kept within a few seconds, or the values before come back.
+// with --safety-revert, so must changes by more than one of the `jump_` sizes.
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct Guard {
+    pub enabled: bool,
+    pub brightness: f64,
+    pub temperature: u16,
+    pub jump_temperature: u16,
+    pub jump_brightness: f64,
+    pub jump_gamma: f64,
+}
+
+impl Default for Guard {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            brightness: 0.3,
+            temperature: 1500,
+            jump_temperature: 2000,
+            jump_brightness: 0.3,
+            jump_gamma: 0.3,
+        }
+    }
+}
+
+impl Guard {
+    pub fn extreme(&self, state: &DaemonState) -> bool {
+        self.enabled && (state.brightness < self.brightness || state.temperature < self.temperature)
+    }
+
+    pub fn jump(&self, before: &DaemonState, after: &DaemonState) -> bool {
+        before.temperature.abs_diff(after.temperature) > self.jump_temperature
+            || (before.brightness - after.brightness).abs() > self.jump_brightness
+            || (before.gamma - after.gamma).abs() > self.jump_gamma
+    }
+}
+
+// the day preset applies from sunrise and the night preset from `twilight_minutes`
+// after sunset. through twilight, before sunrise and after sunset, the values are
+// interpolated between them, as wlsunset does.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schedule {
+    pub enabled: bool,
+    pub sunrise: TimeOfDay,
+    pub sunset: TimeOfDay,
+    pub twilight_minutes: u16,
+    pub day: String,
+    pub night: String,
+    pub invert_from: Option<TimeOfDay>,
+    pub invert_until: Option<TimeOfDay>,
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sunrise: TimeOfDay(7 * 60),
+            sunset: TimeOfDay(19 * 60 + 30),
+            twilight_minutes: 60,
+            day: "day".to_string(),
+            night: "night".to_string(),
+            invert_from: None,
+            invert_until: None,
+        }
+    }
+}
+
+impl Schedule {
+    // minutes from sunrise to sunset.
+    fn day_length(&self) -> u16 {
+        (self.sunset.0 + 1440 - self.sunrise.0) % 1440
+    }
+
+    // how far into the night a minute of the day is, from 0.0 by day to 1.0 by night.
+    pub fn night_fraction(&self, minute: f64) -> f64 {
+        let twilight = self.twilight_minutes as f64;
+        let since_sunrise = (minute - self.sunrise.0 as f64).rem_euclid(1440.0);
+        let since_sunset = since_sunrise - self.day_length() as f64;
+        let until_sunrise = 1440.0 - since_sunrise;
+        if since_sunset <= 0.0 {
+            0.0
+        } else if since_sunset < twilight {
+            since_sunset / twilight
+        } else if until_sunrise < twilight {
+            until_sunrise / twilight
+        } else {
+            1.0
+        }
+    }
+
+    // whether a minute of the day falls between invert_from and invert_until,
+    // or None unless both are set.
+    pub fn inverted(&self, minute: f64) -> Option<bool> {
+        let (from, until) = (self.invert_from?.0 as f64, self.invert_until?.0 as f64);
+        let length = (until - from).rem_euclid(1440.0);
+        Some((minute - from).rem_euclid(1440.0) < length)
+    }
+}
+
+// a preset applied while a window of `app_id` is focused or fullscreen.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub app_id: String,
+    pub preset: String,
+}
+
+// values left out of a preset are not changed when it is applied.
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub temperature: Option<u16>,
+    pub brightness: Option<f64>,
+    pub gamma: Option<f64>,
+    pub inverted: Option<bool>,
+}
+
+impl Preset {
+    pub const NEUTRAL: Preset = Preset {
+        temperature: Some(6500),
+        brightness: Some(1.0),
+        gamma: Some(1.0),
+        inverted: None,
+    };
+
+    // the values a `fraction` of the way to `other`, for the fields both set.
+    // inversion switches halfway.
+    pub fn mix(&self, other: &Preset, fraction: f64) -> Preset {
+        let mix = |a: f64, b: f64| a + (b - a) * fraction;
+        Preset {
+            temperature: self
+                .temperature
+                .zip(other.temperature)
+                .map(|(a, b)| mix(a as f64, b as f64).round() as u16),
+            brightness: self
+                .brightness
+                .zip(other.brightness)
+                .map(|(a, b)| mix(a, b)),
+            gamma: self.gamma.zip(other.gamma).map(|(a, b)| mix(a, b)),
+            inverted: self
+                .inverted
+                .zip(other.inverted)
+                .map(|(a, b)| if fraction < 0.5 { a } else { b }),
+        }
+    }
+
+    // whether the daemon shows this preset's values, up to the rounding presets are saved with.
+    pub fn matches(&self, daemon: &DaemonState) -> bool {
+        self.temperature.is_none_or(|t| t == daemon.temperature)
+            && self
+                .brightness
+                .is_none_or(|b| (b - daemon.brightness).abs() < 0.005)
+            && self.gamma.is_none_or(|g| (g - daemon.gamma).abs() < 0.005)
+            && self.inverted.is_none_or(|i| i == daemon.inverted)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            order: CONTROL_NAMES.iter().map(|name| name.to_string()).collect(),
+            presets: IndexMap::new(),
+            night_preset: "night".to_string(),
+            presets_invert: true,
+            step: Steps::default(),
+            rounding: Rounding::default(),
+            smoothing: Smoothing::default(),
+            link: Link::default(),
+            strength: Strength::default(),
+            gamma_toggle: GammaToggle::default(),
+            buttons: Buttons::default(),
+            theme: Theme::default(),
+            game_mode: GameMode::default(),
+            rules: Vec::new(),
+            auto_brightness: AutoBrightness::default(),
+            schedule: Schedule::default(),
+            timer: Timer::default(),
+            output: IndexMap::new(),
+            guard: Guard::default(),
+            path: None,
+        }
+    }
+}
+
+// config keys can be set in the environment, with sections and keys joined by
+// double underscores, e.g. WL_GAMMARELAY_APPLET__SMOOTHING__SETTLE_MS=200 for
+// settle_ms under [smoothing]. they take precedence over the file. output and
+// preset names keep their case, as in WL_GAMMARELAY_APPLET__OUTPUT__DP-1__GAMMA.
+pub const ENV_PREFIX: &str = "WL_GAMMARELAY_APPLET__";
+
+// set the keys named by the variables carrying the prefix. values are read as
+// TOML, or as strings if they are not. returns whether any were set.
+fn apply_env(
+    table: &mut toml::Table,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<bool, AppletError> {
+    let mut overridden = false;
+    for (name, value) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let mut keys: Vec<String> = Vec::new();
+        for segment in key.split("__") {
+            let named = matches!(keys.last().map(String::as_str), Some("output" | "presets"));
+            keys.push(if named {
+                segment.to_string()
+            } else {
+                segment.to_lowercase()
+            });
+        }
+        let (last, sections) = keys.split_last().expect("rust: split yields a key");
+        let mut current = &mut *table;
+        for section in sections {
+            current = current
+                .entry(section.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| {
+                    AppletError::InvalidConfig(format!("{name}: {section} is not a section"))
+                })?;
+        }
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or(toml::Value::String(value));
+        current.insert(last.clone(), value);
+        overridden = true;
+    }
+    Ok(overridden)
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("wl-gammarelay-applet").join("config.toml"))
+}
+
+impl Config {
+    // the preset the night mode switch applies, if the config defines it.
+    pub fn night_preset(&self) -> Option<Preset> {
+        self.presets.get(&self.night_preset).copied()
+    }
+
+    // the preset the windows call for: the first rule matching a focused or
+    // fullscreen window, then game mode's.
+    pub fn override_preset(&self, toplevels: &[Toplevel]) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                toplevels.iter().any(|toplevel| {
+                    toplevel.app_id == rule.app_id && (toplevel.activated || toplevel.fullscreen)
+                })
+            })
+            .map(|rule| rule.preset.as_str())
+            .or((self.game_mode.enabled && self.game_mode.active(toplevels))
+                .then_some(NEUTRAL_PRESET))
+    }
+
+    // a preset by name, including the built-in neutral one.
+    pub fn preset(&self, name: &str) -> Option<Preset> {
+        self.presets
+            .get(name)
+            .copied()
+            .or((name == NEUTRAL_PRESET).then_some(Preset::NEUTRAL))
+    }
+
+    // the profile of a daemon output, named either as the compositor does,
+    // e.g. "DP-1", or as the daemon does, "DP_1".
+    pub fn output_profile(&self, output: &str) -> Option<&OutputProfile> {
+        self.output
+            .iter()
+            .find(|(name, _)| crate::wayland::daemon_output_name(name) == output)
+            .map(|(_, profile)| profile)
+    }
+
+    // narrow the config to an applet for one output, adding its presets.
+    pub fn select_output(&mut self, output: &str) {
+        if let Some(profile) = self.output_profile(output) {
+            let presets = profile.presets.clone();
+            self.presets.extend(presets);
+        }
+    }
+
+    // the schedule's values at a minute of the day, if it is enabled.
+    pub fn scheduled_preset(&self, minute: f64) -> Option<Preset> {
+        if !self.schedule.enabled {
+            return None;
+        }
+        let day = self.preset(&self.schedule.day)?;
+        let night = self.preset(&self.schedule.night)?;
+        let mut preset = day.mix(&night, self.schedule.night_fraction(minute));
+        if let Some(inverted) = self.schedule.inverted(minute) {
+            preset.inverted = Some(inverted);
+        }
+        Some(preset)
+    }
+
+    // an explicitly requested file must exist; the default location is optional.
+    pub fn load(path: Option<PathBuf>) -> Result<Self, AppletError> {
+        let (path, required) = match path {
+            Some(path) => (Some(path), true),
+            None => (default_config_path(), false),
+        };
+        let text = match path.as_ref().map(std::fs::read_to_string) {
+            Some(Ok(text)) => text,
+            Some(Err(e)) if required || e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e.into())
+            }
+            _ => String::new(),
+        };
+        let mut table: toml::Table = toml::from_str(&text)?;
+        let overridden = apply_env(&mut table, std::env::vars())?;
+        let source = match (&path, overridden) {
+            (Some(path), false) => path.display().to_string(),
+            (Some(path), true) => format!("{} and {ENV_PREFIX}*", path.display()),
+            (None, _) => format!("{ENV_PREFIX}*"),
+        };
+        let mut config = Config::deserialize(table)
+            .map_err(|e| AppletError::InvalidConfig(format!("{source}: {e}")))?;
+        config
+            .validate()
+            .map_err(|message| AppletError::InvalidConfig(format!("{source}: {message}")))?;
+        config.path = path;
+        config.link.points.sort_by_key(|point| point.0);
+        // with presets_invert off, inversion is left to the switch and the schedule.
+        if !config.presets_invert {
+            let profiles = config
+                .output
+                .values_mut()
+                .flat_map(|profile| profile.presets.values_mut());
+            for preset in config.presets.values_mut().chain(profiles) {
+                preset.inverted = None;
+            }
+        }
+        Ok(config)
+    }
+
+    // check every value against what the daemon accepts, naming the field.
+    fn validate(&mut self) -> Result<(), String> {
+        self.normalize_order()?;
+        for (name, preset) in &self.presets {
+            check_preset(&format!("presets.{name}"), preset)?;
+        }
+        for (output, profile) in &self.output {
+            check_preset(&format!("output.\"{output}\""), &profile.values())?;
+            for (name, preset) in &profile.presets {
+                check_preset(&format!("output.\"{output}\".presets.{name}"), preset)?;
+            }
+        }
+        for (index, (temperature, brightness)) in self.link.points.iter().enumerate() {
+            check_range(
+                &format!("link.points[{index}] temperature"),
+                *temperature,
+                &TEMPERATURE_RANGE,
+            )?;
+            check_range(
+                &format!("link.points[{index}] brightness"),
+                *brightness,
+                &BRIGHTNESS_RANGE,
+            )?;
+        }
+        for temperature in [self.strength.temperature.0, self.strength.temperature.1] {
+            check_range("strength.temperature", temperature, &TEMPERATURE_RANGE)?;
+        }
+        for brightness in [self.strength.brightness.0, self.strength.brightness.1] {
+            check_range("strength.brightness", brightness, &BRIGHTNESS_RANGE)?;
+        }
+        for gamma in [self.gamma_toggle.values.0, self.gamma_toggle.values.1] {
+            check_range("gamma_toggle.values", gamma, &GAMMA_RANGE)?;
+        }
+        for (button, action) in self.buttons.bound() {
+            let Some(action) = action else {
+                continue;
+            };
+            match ButtonAction::parse(action) {
+                Ok(ButtonAction::Preset(name)) if self.preset(&name).is_none() => {
+                    return Err(format!("buttons.{button}: no preset named \"{name}\""));
+                }
+                Err(e) => return Err(format!("buttons.{button}: {e}")),
+                Ok(_) => {}
+            }
+        }
+        check_range("step.temperature", self.step.temperature, &(1..=9000))?;
+        for (field, step) in [
+            ("step.brightness", self.step.brightness),
+            ("step.gamma", self.step.gamma),
+            ("step.backlight", self.step.backlight),
+            ("step.ddc", self.step.ddc),
+            ("step.strength", self.step.strength),
+        ] {
+            check_range(field, step, &(0.001..=1.0))?;
+        }
+        check_range(
+            "rounding.temperature",
+            self.rounding.temperature,
+            &(1..=9000),
+        )?;
+        check_range(
+            "rounding.temperature_display",
+            self.rounding.temperature_display,
+            &(1..=9000),
+        )?;
+        check_range("smoothing.settle_ms", self.smoothing.settle_ms, &(0..=5000))?;
+        let auto = &self.auto_brightness;
+        check_range("auto_brightness.target", auto.target, &(0.01..=1.0))?;
+        check_range("auto_brightness.tolerance", auto.tolerance, &(0.0..=1.0))?;
+        check_range("auto_brightness.step", auto.step, &(0.001..=1.0))?;
+        check_range(
+            "auto_brightness.interval_ms",
+            auto.interval_ms,
+            &(100..=3_600_000),
+        )?;
+        check_range(
+            "auto_brightness.min_brightness",
+            auto.min_brightness,
+            &BRIGHTNESS_RANGE,
+        )?;
+        check_range(
+            "auto_brightness.max_brightness",
+            auto.max_brightness,
+            &(auto.min_brightness..=1.0),
+        )?;
+        if self.schedule.enabled {
+            for (field, name) in [
+                ("schedule.day", &self.schedule.day),
+                ("schedule.night", &self.schedule.night),
+            ] {
+                if self.preset(name).is_none() {
+                    return Err(format!("{field}: no preset named \"{name}\""));
+                }
+            }
+            // the morning and evening twilight must fit into the night.
+            check_range(
+                "schedule.twilight_minutes",
+                self.schedule.twilight_minutes,
+                &(0..=(1440 - self.schedule.day_length()) / 2),
+            )?;
+            if self.schedule.invert_from.is_some() != self.schedule.invert_until.is_some() {
+                return Err(
+                    "schedule: set both invert_from and invert_until, or neither".to_string(),
+                );
+            }
+        }
+        check_range("timer.minutes", self.timer.minutes, &(1..=1440))?;
+        check_range("guard.brightness", self.guard.brightness, &BRIGHTNESS_RANGE)?;
+        check_range(
+            "guard.temperature",
+            self.guard.temperature,
+            &TEMPERATURE_RANGE,
+        )?;
+        check_range(
+            "guard.jump_temperature",
+            self.guard.jump_temperature,
+            &(1..=9000),
+        )?;
+        check_range(
+            "guard.jump_brightness",
+            self.guard.jump_brightness,
+            &(0.01..=1.0),
+        )?;
+        check_range("guard.jump_gamma", self.guard.jump_gamma, &(0.01..=1.0))?;
+        if let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| self.preset(&rule.preset).is_none())
+        {
+            return Err(format!(
+                "rules: no preset named \"{}\" for {}",
+                rule.preset, rule.app_id
+            ));
+        }
+        Ok(())
+    }
+
+    // the preset after the one the daemon shows, wrapping around, or the first
+    // preset if none matches.
+    pub fn next_preset(&self, daemon: &DaemonState) -> Option<(&str, Preset)> {
+        let next = self
+            .presets
+            .values()
+            .position(|preset| preset.matches(daemon))
+            .map_or(0, |index| (index + 1) % self.presets.len());
+        self.presets
+            .get_index(next)
+            .map(|(name, preset)| (name.as_str(), *preset))
+    }
+
+    // add or replace a preset in the config file, keeping the rest of the file as
+    // written. the file is created if it does not exist yet.
+    pub fn save_preset(&mut self, name: &str, preset: Preset) -> Result<(), AppletError> {
+        let Some(path) = &self.path else {
+            return Err(AppletError::InvalidConfig(
+                "no config file location; set XDG_CONFIG_HOME or HOME, or pass --config"
+                    .to_string(),
+            ));
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut document: toml_edit::Document = text.parse()?;
+        let presets = document
+            .entry("presets")
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .ok_or_else(|| AppletError::InvalidConfig("presets: not a table".to_string()))?;
+        // write [presets.name] headers rather than an empty [presets] table.
+        presets.set_implicit(true);
+        let mut table = toml_edit::Table::new();
+        if let Some(temperature) = preset.temperature {
+            table["temperature"] = toml_edit::value(temperature as i64);
+        }
+        if let Some(brightness) = preset.brightness {
+            table["brightness"] = toml_edit::value(brightness);
+        }
+        if let Some(gamma) = preset.gamma {
+            table["gamma"] = toml_edit::value(gamma);
+        }
+        if let Some(inverted) = preset.inverted {
+            table["inverted"] = toml_edit::value(inverted);
+        }
+        presets.insert(name, toml_edit::Item::Table(table));
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, document.to_string())?;
+        self.presets.insert(name.to_string(), preset);
+        Ok(())
+    }
+
+    // reject unknown or repeated names, then append any omitted controls in default order.
+    fn normalize_order(&mut self) -> Result<(), String> {
+        for (index, name) in self.order.iter().enumerate() {
+            if !CONTROL_NAMES.contains(&name.as_str()) {
+                return Err(format!("order: unknown control \"{name}\""));
+            }
+            if self.order[..index].contains(name) {
+                return Err(format!("order: control \"{name}\" listed twice"));
+            }
+        }
+        for name in CONTROL_NAMES {
+            if !self.order.iter().any(|n| n == name) {
+                self.order.push(name.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_preset(field: &str, preset: &Preset) -> Result<(), String> {
+    if let Some(temperature) = preset.temperature {
+        check_range(
+            &format!("{field}.temperature"),
+            temperature,
+            &TEMPERATURE_RANGE,
+        )?;
+    }
+    if let Some(brightness) = preset.brightness {
+        check_range(
+            &format!("{field}.brightness"),
+            brightness,
+            &BRIGHTNESS_RANGE,
+        )?;
+    }
+    if let Some(gamma) = preset.gamma {
+        check_range(&format!("{field}.gamma"), gamma, &GAMMA_RANGE)?;
+    }
+    Ok(())
+}
+
+pub fn check_range<T: PartialOrd + Display>(
+    field: &str,
+    value: T,
+    range: &RangeInclusive<T>,
+) -> Result<(), String> {
+    if range.contains(&value) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{field} is {value}, but must be within {} - {}",
+            range.start(),
+            range.end()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_switches_invert() {
+        let mut config = Config::default();
+        for name in ["day", "night"] {
+            config.presets.insert(name.to_string(), Preset::NEUTRAL);
+        }
+        config.schedule.enabled = true;
+        config.schedule.invert_from = Some(TimeOfDay(23 * 60));
+        config.schedule.invert_until = Some(TimeOfDay(5 * 60));
+        let inverted = |minute: u16| {
+            config
+                .scheduled_preset(minute as f64)
+                .and_then(|preset| preset.inverted)
+        };
+        assert_eq!(inverted(23 * 60 + 30), Some(true));
+        assert_eq!(inverted(4 * 60), Some(true));
+        assert_eq!(inverted(5 * 60), Some(false));
+        assert_eq!(inverted(12 * 60), Some(false));
+    }
+
+    #[test]
+    fn environment_overrides_config_keys() {
+        let mut table: toml::Table =
+            toml::from_str("[smoothing]\nsettle_ms = 100").expect("rust: valid toml");
+        let vars = [
+            ("WL_GAMMARELAY_APPLET__SMOOTHING__SETTLE_MS", "200"),
+            ("WL_GAMMARELAY_APPLET__OUTPUT__DP-1__GAMMA", "0.9"),
+            ("WL_GAMMARELAY_APPLET__PRESETS__Night__TEMPERATURE", "3000"),
+            ("WL_GAMMARELAY_APPLET_FADE_GRACE_MS", "250"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        assert!(apply_env(&mut table, vars.into_iter()).expect("rust: sections exist"));
+        let config = Config::deserialize(table).expect("rust: valid config");
+        assert_eq!(config.smoothing.settle_ms, 200);
+        assert_eq!(config.output["DP-1"].gamma, Some(0.9));
+        assert_eq!(config.presets["Night"].temperature, Some(3000));
+    }
+
+    #[test]
+    fn environment_values_are_checked_like_the_file() {
+        let mut table = toml::Table::new();
+        let vars = [("WL_GAMMARELAY_APPLET__PRESETS__night__BRIGHTNESS", "1.5")]
+            .map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env(&mut table, vars.into_iter()).expect("rust: sections exist");
+        let mut config = Config::deserialize(table).expect("rust: valid types");
+        assert!(config.validate().is_err());
+    }
+}
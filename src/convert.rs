@@ -0,0 +1,178 @@
+use crate::config::{BRIGHTNESS_RANGE, GAMMA_RANGE, TEMPERATURE_RANGE};
+
+// remapping values from low1-high1 to low2-high2 is done like
+// low2 + (value - low1) * (high2 - low2) / (high1 - low1)
+
+// 1000 - 10000 to
+//  0.0 - 1.0
+pub fn dbus_temperature_to_ui_value(dbus_value: u16) -> f64 {
+    (dbus_value as f64 - 1000.0) * (1.0 / 9000.0)
+}
+pub fn ui_temperature_to_dbus_value(ui_value: f64) -> u16 {
+    (1000.0 + ui_value * 9000.0) as u16
+}
+pub fn ui_temperature_delta_to_dbus_value(ui_value: f64) -> i16 {
+    (ui_value * 9000.0) as i16
+}
+pub fn dbus_temperature_to_string(dbus_value: i16, rounding: u16) -> String {
+    let rounded = (dbus_value as f64 / rounding as f64).round() as i16 * rounding as i16;
+    format!("{rounded} K")
+}
+pub fn dbus_temperature_rounded(dbus_value: i16, rounding: u16) -> i16 {
+    ((dbus_value as f64 / rounding as f64) as i16) * rounding as i16
+}
+
+pub fn dbus_brightness_to_ui_value(dbus_value: f64) -> f64 {
+    dbus_value
+}
+pub fn ui_brightness_delta_to_dbus_value(ui_value: f64) -> f64 {
+    ui_value
+}
+// raw shows the value as the daemon reports it, e.g. for checking against busctl.
+pub fn dbus_brightness_to_string(dbus_value: f64, raw: bool) -> String {
+    if raw {
+        return format!("{dbus_value:.2}");
+    }
+    let percentage = dbus_value * 100.0;
+    format!("{percentage:3.0} %")
+}
+pub fn dbus_brightness_rounded(dbus_value: f64) -> f64 {
+    (dbus_value * 100.0).round() / 100.0
+}
+
+// 0.5 - 1.5 to
+// 0.0 - 1.0
+pub fn dbus_gamma_to_ui_value(dbus_value: f64) -> f64 {
+    dbus_value - 0.5
+}
+pub fn ui_gamma_to_dbus_value(ui_value: f64) -> f64 {
+    ui_value + 0.5
+}
+pub fn dbus_gamma_to_string(dbus_value: f64) -> String {
+    format!("{dbus_value:.2} γ")
+}
+pub fn dbus_gamma_rounded(dbus_value: f64) -> f64 {
+    (dbus_value * 100.0).round() / 100.0
+}
+
+pub fn backlight_to_string(value: f64) -> String {
+    let percentage = value * 100.0;
+    format!("{percentage:3.0} % ☀")
+}
+
+pub fn strength_to_string(value: f64) -> String {
+    let percentage = value * 100.0;
+    format!("{percentage:3.0} % ◐")
+}
+
+pub fn ddc_to_string(value: f64, label: &str) -> String {
+    let percentage = value * 100.0;
+    format!("{percentage:3.0} % {label}")
+}
+
+// a slider's value without the rounding of the value row, e.g. "4537 K" or
+// "85.3 %". the ends of the sliders are the limits of the daemon's ranges.
+pub fn exact_value_to_string(name: &str, ui_value: f64) -> String {
+    let remap = |low: f64, high: f64| low + ui_value * (high - low);
+    match name {
+        "temperature" => format!(
+            "{} K",
+            remap(
+                *TEMPERATURE_RANGE.start() as f64,
+                *TEMPERATURE_RANGE.end() as f64
+            )
+            .round()
+        ),
+        "brightness" => format!(
+            "{} %",
+            (remap(*BRIGHTNESS_RANGE.start(), *BRIGHTNESS_RANGE.end()) * 1000.0).round() / 10.0
+        ),
+        "gamma" => format!(
+            "{} γ",
+            (remap(*GAMMA_RANGE.start(), *GAMMA_RANGE.end()) * 1000.0).round() / 1000.0
+        ),
+        _ => format!("{} %", (ui_value * 1000.0).round() / 10.0),
+    }
+}
+
+// the sliders are 0 - 1 and the daemon has its own units; values must survive
+// the trip both ways, and land on the ends of the daemon's ranges exactly.
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn slider_ends_are_the_daemon_limits() {
+        assert_eq!(ui_temperature_to_dbus_value(0.0), 1000);
+        assert_eq!(ui_temperature_to_dbus_value(1.0), 10000);
+        assert_eq!(dbus_temperature_to_ui_value(1000), 0.0);
+        assert_eq!(dbus_temperature_to_ui_value(10000), 1.0);
+        assert_eq!(ui_gamma_to_dbus_value(0.0), 0.5);
+        assert_eq!(ui_gamma_to_dbus_value(1.0), 1.5);
+        assert_eq!(exact_value_to_string("temperature", 1.0), "10000 K");
+        assert_eq!(exact_value_to_string("gamma", 0.0), "0.5 γ");
+    }
+
+    proptest! {
+        #[test]
+        fn temperature_survives_the_slider(temperature in 1000u16..=10000) {
+            let ui_value = dbus_temperature_to_ui_value(temperature);
+            prop_assert!((0.0..=1.0).contains(&ui_value));
+            prop_assert_eq!(ui_temperature_to_dbus_value(ui_value), temperature);
+        }
+
+        #[test]
+        fn temperature_deltas_keep_their_size(delta in -9000i16..=9000) {
+            let ui_delta =
+                dbus_temperature_to_ui_value(1000 + delta.unsigned_abs()) * delta.signum() as f64;
+            prop_assert_eq!(ui_temperature_delta_to_dbus_value(ui_delta), delta);
+        }
+
+        #[test]
+        fn gamma_survives_the_slider(gamma in 0.5f64..=1.5) {
+            let ui_value = dbus_gamma_to_ui_value(gamma);
+            prop_assert!((0.0..=1.0).contains(&ui_value));
+            prop_assert!((ui_gamma_to_dbus_value(ui_value) - gamma).abs() < 1e-12);
+        }
+
+        #[test]
+        fn brightness_is_the_slider_value(brightness in 0.0f64..=1.0) {
+            prop_assert_eq!(dbus_brightness_to_ui_value(brightness), brightness);
+            prop_assert_eq!(ui_brightness_delta_to_dbus_value(brightness), brightness);
+        }
+
+        // applied temperatures drop the remainder toward 0, so a slider move
+        // never overshoots; the shown ones round to the nearest step.
+        #[test]
+        fn temperature_rounding_stays_within_a_step(
+            value in -9000i16..=10000,
+            rounding in prop::sample::select(vec![1u16, 10, 50, 100, 500]),
+        ) {
+            let rounded = dbus_temperature_rounded(value, rounding);
+            prop_assert_eq!(rounded % rounding as i16, 0);
+            prop_assert!(rounded.abs() <= value.abs());
+            prop_assert!((value - rounded).abs() < rounding as i16);
+            let shown: i16 = dbus_temperature_to_string(value, rounding)
+                .trim_end_matches(" K")
+                .parse()
+                .expect("rust: shown temperature");
+            prop_assert!((value - shown).abs() * 2 <= rounding as i16);
+        }
+
+        #[test]
+        fn rounding_keeps_ranges_and_is_stable(
+            brightness in 0.0f64..=1.0,
+            gamma in 0.5f64..=1.5,
+        ) {
+            let rounded = dbus_brightness_rounded(brightness);
+            prop_assert!((0.0..=1.0).contains(&rounded));
+            prop_assert!((rounded - brightness).abs() <= 0.005 + 1e-12);
+            prop_assert_eq!(dbus_brightness_rounded(rounded), rounded);
+            let rounded = dbus_gamma_rounded(gamma);
+            prop_assert!((0.5..=1.5).contains(&rounded));
+            prop_assert_eq!(dbus_gamma_rounded(rounded), rounded);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    str::FromStr,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use zbus::{
+    blocking::{
+        fdo::{DBusProxy, IntrospectableProxy},
+        Connection,
+    },
+    names::WellKnownName,
+    xml::Node,
+};
+
+use crate::{AppletError, GammaRelayProxyBlocking};
+
+pub const DAEMON_SERVICE: &str = "rs.wl-gammarelay";
+const DAEMON_INTERFACE: &str = "rs.wl.gammarelay";
+
+// what the running daemon offers, learned from introspection, so the applet
+// can adapt to older or newer versions of wl-gammarelay-rs.
+pub struct Capabilities {
+    pub version: Option<String>,
+    pub methods: Vec<String>,
+    pub properties: Vec<String>,
+    // None if the daemon has no per-output objects under /outputs.
+    pub outputs: Option<Vec<String>>,
+}
+
+// how long the daemon may take to appear, e.g. while the session starts up.
+static STARTUP_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+
+pub fn init(timeout: Duration) {
+    let _ = STARTUP_TIMEOUT.set(timeout);
+}
+
+// wait until the daemon is running or can be started by DBus activation, so
+// a missing daemon fails quickly with a clear error rather than a call timeout.
+pub fn wait_for_daemon(connection: &Connection) -> Result<(), AppletError> {
+    let dbus = DBusProxy::new(connection)?;
+    let name = WellKnownName::from_static_str(DAEMON_SERVICE).map_err(zbus::Error::from)?;
+    let deadline = Instant::now() + STARTUP_TIMEOUT.get().copied().unwrap_or_default();
+    loop {
+        if dbus.name_has_owner(name.clone().into())?
+            || dbus
+                .list_activatable_names()?
+                .iter()
+                .any(|activatable| activatable.as_str() == DAEMON_SERVICE)
+        {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            return Err(AppletError::NoDaemon);
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+}
+
+fn introspect(connection: &Connection, path: &str) -> Result<Node, AppletError> {
+    let xml = IntrospectableProxy::builder(connection)
+        .destination(DAEMON_SERVICE)?
+        .path(path)?
+        .build()?
+        .introspect()?;
+    Ok(Node::from_str(&xml)?)
+}
+
+impl Capabilities {
+    pub fn detect(proxy: &GammaRelayProxyBlocking) -> Result<Self, AppletError> {
+        let connection = proxy.inner().connection();
+        let root = introspect(connection, "/")?;
+        let interface = root
+            .interfaces()
+            .into_iter()
+            .find(|interface| interface.name() == DAEMON_INTERFACE);
+        let methods: Vec<String> = interface
+            .map(|i| i.methods().iter().map(|m| m.name().to_string()).collect())
+            .unwrap_or_default();
+        let properties: Vec<String> = interface
+            .map(|i| {
+                i.properties()
+                    .iter()
+                    .map(|p| p.name().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // no released daemon exposes a version yet; read it if one does.
+        let version = if properties.iter().any(|p| p == "Version") {
+            proxy.inner().get_property::<String>("Version").ok()
+        } else {
+            None
+        };
+
+        let has_outputs = root
+            .nodes()
+            .iter()
+            .any(|node| node.name() == Some("outputs"));
+        let outputs = if has_outputs {
+            let node = introspect(connection, "/outputs")?;
+            Some(
+                node.nodes()
+                    .iter()
+                    .filter_map(|node| node.name().map(str::to_string))
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        Ok(Self {
+            version,
+            methods,
+            properties,
+            outputs,
+        })
+    }
+
+    // a control needs both its property and its update method. grayscale, a
+    // filter only some daemons have, is written through its property.
+    pub fn supports_control(&self, name: &str) -> bool {
+        let (property, method) = match name {
+            "invert" => ("Inverted", "ToggleInverted"),
+            "temperature" => ("Temperature", "UpdateTemperature"),
+            "brightness" => ("Brightness", "UpdateBrightness"),
+            "gamma" => ("Gamma", "UpdateGamma"),
+            "grayscale" => return self.properties.iter().any(|p| p == "Grayscale"),
+            _ => return true,
+        };
+        self.properties.iter().any(|p| p == property) && self.methods.iter().any(|m| m == method)
+    }
+
+    pub fn version_string(&self) -> &str {
+        self.version.as_deref().unwrap_or("unknown")
+    }
+}
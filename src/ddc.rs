@@ -0,0 +1,99 @@
+use std::{
+    sync::mpsc::{channel, Sender},
+    thread::spawn,
+};
+
+use crate::{sandbox, AppletError};
+
+// VCP feature code for luminance (brightness).
+const VCP_BRIGHTNESS: &str = "10";
+
+// an external monitor reachable over DDC/CI through the `ddcutil` command.
+// writes are slow, so they go to a per-display thread that only applies the newest value.
+pub struct DdcDisplay {
+    pub label: String,
+    pub value: f64,
+    max: u32,
+    writer: Sender<u32>,
+}
+
+fn ddcutil(args: &[&str]) -> Result<String, AppletError> {
+    let output = sandbox::host_command("ddcutil").args(args).output()?;
+    if !output.status.success() {
+        return Err(AppletError::Ddc(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// parse display numbers from `ddcutil detect --brief`, skipping "Invalid display" entries.
+fn parse_detect(text: &str) -> Vec<u32> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("Display "))
+        .filter_map(|number| number.trim().parse().ok())
+        .collect()
+}
+
+// parse `ddcutil --brief getvcp 10`, e.g. "VCP 10 C 60 100", into (current, max).
+fn parse_getvcp(text: &str) -> Option<(u32, u32)> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    match fields.as_slice() {
+        ["VCP", _, "C", current, max, ..] => Some((current.parse().ok()?, max.parse().ok()?)),
+        _ => None,
+    }
+}
+
+impl DdcDisplay {
+    // probe all displays; those that do not report brightness are skipped.
+    pub fn detect() -> Result<Vec<Self>, AppletError> {
+        let mut displays = Vec::new();
+        for number in parse_detect(&ddcutil(&["detect", "--brief"])?) {
+            let display = number.to_string();
+            let Ok(text) = ddcutil(&["--display", &display, "--brief", "getvcp", VCP_BRIGHTNESS])
+            else {
+                continue;
+            };
+            let Some((current, max)) = parse_getvcp(&text) else {
+                continue;
+            };
+            if max == 0 {
+                continue;
+            }
+
+            let (writer, receiver) = channel::<u32>();
+            spawn(move || {
+                while let Ok(mut value) = receiver.recv() {
+                    while let Ok(newer) = receiver.try_recv() {
+                        value = newer;
+                    }
+                    if let Err(e) = ddcutil(&[
+                        "--display",
+                        &display,
+                        "setvcp",
+                        VCP_BRIGHTNESS,
+                        &value.to_string(),
+                    ]) {
+                        eprintln!("rust: set ddc brightness on display {display}: {e}");
+                    }
+                }
+            });
+
+            displays.push(Self {
+                label: format!("🖵{number}"),
+                value: current as f64 / max as f64,
+                max,
+                writer,
+            });
+        }
+        Ok(displays)
+    }
+
+    // queue brightness from 0.0 - 1.0
+    pub fn set_value(&mut self, value: f64) {
+        self.value = value.clamp(0.0, 1.0);
+        let _ = self
+            .writer
+            .send((self.value * self.max as f64).round() as u32);
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    io::Read,
+    os::{
+        fd::{AsFd, AsRawFd},
+        unix::{fs::FileExt, net::UnixStream},
+    },
+    sync::mpsc::Receiver,
+};
+
+use wayland_client::{
+    backend::WaylandError,
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_buffer, wl_compositor, wl_pointer, wl_registry, wl_seat, wl_shm, wl_shm_pool, wl_surface,
+    },
+    Connection, Dispatch, DispatchError, EventQueue, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+
+use crate::{config::TEMPERATURE_RANGE, wayland, worker::DaemonState, AppletError};
+
+// the screen edge the --dock strip sits on, at its middle.
+#[derive(Clone, Copy, Debug, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    fn vertical(self) -> bool {
+        matches!(self, Edge::Left | Edge::Right)
+    }
+
+    fn anchor(self) -> Anchor {
+        match self {
+            Edge::Top => Anchor::Top,
+            Edge::Bottom => Anchor::Bottom,
+            Edge::Left => Anchor::Left,
+            Edge::Right => Anchor::Right,
+        }
+    }
+
+    // width and height of the strip.
+    fn size(self) -> (u32, u32) {
+        if self.vertical() {
+            (THICKNESS, LENGTH)
+        } else {
+            (LENGTH, THICKNESS)
+        }
+    }
+}
+
+// the strip runs LENGTH along the edge: the invert dot in a square at its
+// start, then the temperature bar.
+const LENGTH: u32 = 160;
+const THICKNESS: u32 = 10;
+const DOT: u32 = 6;
+const INSET: u32 = 2;
+// linux input event code of the left mouse button.
+const BTN_LEFT: u32 = 0x110;
+// premultiplied argb.
+const BACKGROUND: u32 = 0xcc0d0d0d;
+const TRACK: u32 = 0xff404040;
+const FOREGROUND: u32 = 0xffffffff;
+// the bar goes from amber at the warmest temperature to pale blue at the coolest.
+const WARM: [u32; 3] = [0xff, 0x8a, 0x2a];
+const COOL: [u32; 3] = [0xcf, 0xe0, 0xff];
+
+// the strip's pixels as little-endian argb.
+pub fn render(state: &DaemonState, edge: Edge) -> Vec<u8> {
+    let (width, height) = edge.size();
+    let mut pixels = vec![BACKGROUND; (width * height) as usize];
+    // positions along and across the strip, turned for a vertical one so the
+    // bar fills upward.
+    let mut fill = |along: u32, across: u32, length: u32, breadth: u32, color: u32| {
+        for a in along..along + length {
+            for b in across..across + breadth {
+                let (x, y) = if edge.vertical() {
+                    (b, LENGTH - 1 - a)
+                } else {
+                    (a, b)
+                };
+                pixels[(y * width + x) as usize] = color;
+            }
+        }
+    };
+    let offset = (THICKNESS - DOT) / 2;
+    let dot = if state.inverted { FOREGROUND } else { TRACK };
+    fill(offset, offset, DOT, DOT, dot);
+
+    let start = THICKNESS + INSET;
+    let length = LENGTH - start - INSET;
+    let (low, high) = (*TEMPERATURE_RANGE.start(), *TEMPERATURE_RANGE.end());
+    let fraction = (state.temperature.clamp(low, high) - low) as f64 / (high - low) as f64;
+    let channel = |i: usize| (WARM[i] as f64 + (COOL[i] as f64 - WARM[i] as f64) * fraction) as u32;
+    let color = 0xff000000 | channel(0) << 16 | channel(1) << 8 | channel(2);
+    fill(start, INSET, length, THICKNESS - 2 * INSET, TRACK);
+    let filled = (length as f64 * fraction).round() as u32;
+    fill(start, INSET, filled, THICKNESS - 2 * INSET, color);
+    pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .collect()
+}
+
+#[derive(Default)]
+struct State {
+    configured: bool,
+    closed: bool,
+    clicked: bool,
+    pointer: Option<wl_pointer::WlPointer>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer_surface.ack_configure(serial);
+                state.configured = true;
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn event(
+        state: &mut Self,
+        seat: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            if capabilities.contains(wl_seat::Capability::Pointer) && state.pointer.is_none() {
+                state.pointer = Some(seat.get_pointer(qhandle, ()));
+            }
+        }
+    }
+}
+
+// the pointer only reaches the strip, so any left press is a click on it.
+impl Dispatch<wl_pointer::WlPointer, ()> for State {
+    fn event(
+        state: &mut Self,
+        _pointer: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_pointer::Event::Button {
+            button: BTN_LEFT,
+            state: WEnum::Value(wl_pointer::ButtonState::Pressed),
+            ..
+        } = event
+        {
+            state.clicked = true;
+        }
+    }
+}
+
+// each drawing gets its own buffer, dropped once the compositor is done with it.
+impl Dispatch<wl_buffer::WlBuffer, ()> for State {
+    fn event(
+        _state: &mut Self,
+        buffer: &wl_buffer::WlBuffer,
+        event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+delegate_noop!(State: wl_compositor::WlCompositor);
+delegate_noop!(State: wl_shm_pool::WlShmPool);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ignore wl_shm::WlShm);
+delegate_noop!(State: ignore wl_surface::WlSurface);
+
+fn draw(
+    queue: &EventQueue<State>,
+    shm: &wl_shm::WlShm,
+    surface: &wl_surface::WlSurface,
+    state: &DaemonState,
+    edge: Edge,
+) -> Result<(), AppletError> {
+    let data = render(state, edge);
+    let (width, height) = edge.size();
+    let file = wayland::shm_file("dock", data.len() as u64)?;
+    file.write_all_at(&data, 0)?;
+    let qhandle = queue.handle();
+    let pool = shm.create_pool(file.as_fd(), data.len() as i32, &qhandle, ());
+    let buffer = pool.create_buffer(
+        0,
+        width as i32,
+        height as i32,
+        (width * 4) as i32,
+        wl_shm::Format::Argb8888,
+        &qhandle,
+        (),
+    );
+    pool.destroy();
+    surface.attach(Some(&buffer), 0, 0);
+    surface.damage(0, 0, width as i32, height as i32);
+    surface.commit();
+    Ok(())
+}
+
+// show the daemon values received in a slim strip on a screen edge, above
+// windows, and call `clicked` when it is clicked. a byte on `wake` announces
+// new values, so the thread sleeps until either the compositor or the daemon
+// has something. runs until the compositor closes the strip, so it belongs on
+// its own thread. needs a compositor with wlr-layer-shell, such as sway or hyprland.
+pub fn run(
+    edge: Edge,
+    states: Receiver<DaemonState>,
+    mut wake: UnixStream,
+    mut clicked: impl FnMut(),
+) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+    let qhandle = queue.handle();
+    let compositor: wl_compositor::WlCompositor = globals.bind(&qhandle, 1..=4, ())?;
+    let shm: wl_shm::WlShm = globals.bind(&qhandle, 1..=1, ())?;
+    let layer_shell: ZwlrLayerShellV1 = globals.bind(&qhandle, 1..=4, ())?;
+    let _seat: wl_seat::WlSeat = globals.bind(&qhandle, 1..=7, ())?;
+
+    let (width, height) = edge.size();
+    let surface = compositor.create_surface(&qhandle, ());
+    let layer_surface = layer_shell.get_layer_surface(
+        &surface,
+        None,
+        Layer::Top,
+        "wl-gammarelay-applet-dock".to_string(),
+        &qhandle,
+        (),
+    );
+    layer_surface.set_size(width, height);
+    layer_surface.set_anchor(edge.anchor());
+    layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+    surface.commit();
+
+    let mut state = State::default();
+    while !state.configured && !state.closed {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    // nothing is drawn until the first values arrive.
+    while !state.closed {
+        queue.dispatch_pending(&mut state)?;
+        if std::mem::take(&mut state.clicked) {
+            clicked();
+        }
+        queue.flush().map_err(DispatchError::from)?;
+        let Some(guard) = queue.prepare_read() else {
+            continue;
+        };
+        let mut fds = [
+            libc::pollfd {
+                fd: guard.connection_fd().as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: wake.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error.into());
+        }
+        if fds[0].revents != 0 {
+            match guard.read() {
+                Err(WaylandError::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                result => {
+                    result.map_err(DispatchError::from)?;
+                }
+            }
+        } else {
+            drop(guard);
+        }
+        if fds[1].revents != 0 {
+            // the sender is gone once reads return nothing.
+            if wake.read(&mut [0; 64])? == 0 {
+                break;
+            }
+            if let Some(latest) = states.try_iter().last() {
+                draw(&queue, &shm, &surface, &latest, edge)?;
+            }
+        }
+    }
+    layer_surface.destroy();
+    surface.destroy();
+    queue.roundtrip(&mut state)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: DaemonState = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: 5500,
+        brightness: 0.8,
+        gamma: 1.0,
+    };
+
+    #[test]
+    fn dock_strip_shows_temperature_and_invert() {
+        let pixel = |data: &[u8], width: usize, x: usize, y: usize| {
+            let at = (y * width + x) * 4;
+            u32::from_le_bytes(data[at..at + 4].try_into().expect("rust: four bytes"))
+        };
+        let inverted = DaemonState {
+            inverted: true,
+            ..START
+        };
+        let across = render(&inverted, Edge::Bottom);
+        assert_eq!(across.len(), 160 * 10 * 4);
+        // the dot is lit, and 5500 K fills the bar halfway.
+        assert_eq!(pixel(&across, 160, 5, 5), 0xffffffff);
+        assert_ne!(pixel(&across, 160, 80, 5), 0xff404040);
+        assert_eq!(pixel(&across, 160, 150, 5), 0xff404040);
+        // a vertical strip fills from the bottom up.
+        let upright = render(&START, Edge::Left);
+        assert_eq!(pixel(&upright, 10, 5, 154), 0xff404040);
+        assert_eq!(pixel(&upright, 10, 5, 10), 0xff404040);
+        assert_ne!(pixel(&upright, 10, 5, 140), 0xff404040);
+    }
+}
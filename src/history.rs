@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+use crate::worker::DaemonState;
+
+// how many changes can be undone.
+const LIMIT: usize = 50;
+// changes closer together than this, like the steps of one drag or scroll,
+// are undone as one.
+const GROUP: Duration = Duration::from_secs(1);
+
+// daemon values from before the changes made in this session, for undo and redo.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<DaemonState>,
+    redo: Vec<DaemonState>,
+    last_change: Option<Instant>,
+}
+
+impl History {
+    // note a change by hand, given the values it started from.
+    pub fn record(&mut self, before: DaemonState) {
+        let now = Instant::now();
+        let grouped = self
+            .last_change
+            .is_some_and(|last| now.duration_since(last) < GROUP);
+        self.last_change = Some(now);
+        if grouped {
+            return;
+        }
+        self.redo.clear();
+        if self.undo.last() != Some(&before) {
+            self.undo.push(before);
+        }
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    // the values to go back to, if any; `current` can be restored by redo.
+    pub fn undo(&mut self, current: DaemonState) -> Option<DaemonState> {
+        let state = self.undo.pop()?;
+        self.redo.push(current);
+        self.last_change = None;
+        Some(state)
+    }
+
+    pub fn redo(&mut self, current: DaemonState) -> Option<DaemonState> {
+        let state = self.redo.pop()?;
+        self.undo.push(current);
+        self.last_change = None;
+        Some(state)
+    }
+
+    // the values before the change by hand that is still going on, if any.
+    pub fn current_change(&self) -> Option<DaemonState> {
+        self.last_change
+            .is_some_and(|last| last.elapsed() < GROUP)
+            .then(|| self.undo.last().copied())
+            .flatten()
+    }
+
+    // make the next change by hand a change of its own.
+    pub fn end_change(&mut self) {
+        self.last_change = None;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    os::{fd::AsFd, unix::fs::FileExt},
+    sync::mpsc::Receiver,
+};
+
+use wayland_client::{
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_buffer, wl_compositor, wl_region, wl_registry, wl_shm, wl_shm_pool, wl_surface},
+    Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+
+use crate::{wayland, AppletError};
+
+#[derive(Default)]
+struct State {
+    configured: bool,
+    closed: bool,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer_surface.ack_configure(serial);
+                state.configured = true;
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: wl_compositor::WlCompositor);
+delegate_noop!(State: wl_region::WlRegion);
+delegate_noop!(State: wl_shm_pool::WlShmPool);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ZwpIdleInhibitManagerV1);
+delegate_noop!(State: ZwpIdleInhibitorV1);
+delegate_noop!(State: ignore wl_buffer::WlBuffer);
+delegate_noop!(State: ignore wl_shm::WlShm);
+delegate_noop!(State: ignore wl_surface::WlSurface);
+
+// keep the screen from idling, e.g. locking or dimming, while `true` was sent
+// last, as while a slider is dragged. the inhibitor needs a surface that is
+// shown, and the applet's window belongs to the toolkit's own connection, so
+// it sits on a transparent pixel of its own that clicks go through. runs until
+// the sender is dropped, so it belongs on its own thread. needs a compositor
+// with idle-inhibit and wlr-layer-shell, such as sway or hyprland.
+pub fn run(requests: Receiver<bool>) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+    let qhandle = queue.handle();
+    let compositor: wl_compositor::WlCompositor = globals.bind(&qhandle, 1..=4, ())?;
+    let shm: wl_shm::WlShm = globals.bind(&qhandle, 1..=1, ())?;
+    let layer_shell: ZwlrLayerShellV1 = globals.bind(&qhandle, 1..=4, ())?;
+    let manager: ZwpIdleInhibitManagerV1 = globals.bind(&qhandle, 1..=1, ())?;
+
+    // one transparent pixel, premultiplied argb.
+    let file = wayland::shm_file("inhibit", 4)?;
+    file.write_all_at(&[0; 4], 0)?;
+    let pool = shm.create_pool(file.as_fd(), 4, &qhandle, ());
+    let buffer = pool.create_buffer(0, 1, 1, 4, wl_shm::Format::Argb8888, &qhandle, ());
+    pool.destroy();
+
+    let mut state = State::default();
+    while let Ok(inhibit) = requests.recv() {
+        if !inhibit {
+            continue;
+        }
+        let surface = compositor.create_surface(&qhandle, ());
+        let region = compositor.create_region(&qhandle, ());
+        surface.set_input_region(Some(&region));
+        region.destroy();
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            None,
+            Layer::Overlay,
+            "wl-gammarelay-applet-inhibit".to_string(),
+            &qhandle,
+            (),
+        );
+        layer_surface.set_size(1, 1);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+        surface.commit();
+        state.configured = false;
+        state.closed = false;
+        while !state.configured && !state.closed {
+            queue.blocking_dispatch(&mut state)?;
+        }
+        if state.closed {
+            layer_surface.destroy();
+            surface.destroy();
+            continue;
+        }
+        let inhibitor = manager.create_inhibitor(&surface, &qhandle, ());
+        surface.attach(Some(&buffer), 0, 0);
+        surface.commit();
+        queue.roundtrip(&mut state)?;
+
+        // hold it until the drag ends, or the applet does.
+        while let Ok(true) = requests.recv() {}
+        inhibitor.destroy();
+        layer_surface.destroy();
+        surface.destroy();
+        queue.roundtrip(&mut state)?;
+    }
+    Ok(())
+}
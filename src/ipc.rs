@@ -0,0 +1,238 @@
+use std::{
+    cell::Cell,
+    sync::{Arc, Mutex},
+};
+
+use slint::ComponentHandle;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use zbus::{
+    blocking::fdo::DBusProxy, dbus_interface, fdo, names::WellKnownName, Connection,
+    ConnectionBuilder,
+};
+
+use crate::{config::CONTROL_NAMES, Parameters, Startup, WlGammaRelayApplet};
+
+pub const APPLET_SERVICE: &str = "rs.wl-gammarelay-applet";
+pub const APPLET_PATH: &str = "/";
+const APPLET_INTERFACE: &str = "rs.wl.gammarelay.applet";
+
+// DBus interface served by a running applet so scripts can reshape it, e.g.
+// busctl --user call rs.wl-gammarelay-applet / rs.wl.gammarelay.applet ShowControl sb gamma false
+pub struct AppletInterface {
+    app_weak: Arc<Mutex<slint::Weak<WlGammaRelayApplet>>>,
+    window: WindowState,
+}
+
+// the window as bar indicators and scripts see it through the interface.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct WindowState {
+    pub visible: bool,
+    // in hundredths, so a fade sends a few dozen changes rather than one per tick.
+    pub opacity: f64,
+    // kept from fading: --never-fade, shift held, a preset name being typed or a keep? countdown.
+    pub pinned: bool,
+}
+
+// hands window states from the event loop to the interface, only when they change.
+#[derive(Default)]
+pub struct WindowPublisher {
+    sender: Option<UnboundedSender<WindowState>>,
+    last: Cell<WindowState>,
+}
+
+impl WindowPublisher {
+    pub fn new(sender: UnboundedSender<WindowState>) -> Self {
+        Self {
+            sender: Some(sender),
+            last: Cell::default(),
+        }
+    }
+
+    pub fn publish(&self, state: WindowState) {
+        if self.last.replace(state) == state {
+            return;
+        }
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(state);
+        }
+    }
+}
+
+fn set_control_visible(app: &WlGammaRelayApplet, name: &str, visible: bool) {
+    let startup = app.global::<Startup>();
+    match name {
+        "invert" => startup.set_show_invert(visible),
+        "temperature" => startup.set_show_temperature(visible),
+        "brightness" => startup.set_show_brightness(visible),
+        "gamma" => startup.set_show_gamma(visible),
+        "strength" => startup.set_show_strength(visible),
+        "backlight" => startup.set_show_backlight(visible && startup.get_backlight_available()),
+        "ddc" => startup.set_show_ddc(visible),
+        _ => {}
+    }
+}
+
+#[dbus_interface(name = "rs.wl.gammarelay.applet")]
+impl AppletInterface {
+    /// ShowControl method
+    fn show_control(&self, name: String, visible: bool) -> fdo::Result<()> {
+        if !CONTROL_NAMES.contains(&name.as_str()) {
+            return Err(fdo::Error::InvalidArgs(format!("unknown control: {name}")));
+        }
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(move |app| {
+                set_control_visible(&app, &name, visible);
+                crate::apply_control_order(&app);
+            })
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// CyclePreset method
+    fn cycle_preset(&self) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(|app| app.global::<Parameters>().invoke_cycle_preset())
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// ToggleGamma method
+    fn toggle_gamma(&self) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(|app| app.global::<Parameters>().invoke_toggle_gamma())
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// ApplyPreset method
+    fn apply_preset(&self, name: String) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(move |app| {
+                app.global::<Parameters>().invoke_apply_preset(name.into())
+            })
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Temporary method
+    fn temporary(&self, name: String, minutes: u32) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(move |app| {
+                app.global::<Parameters>()
+                    .invoke_temporary_preset(name.into(), minutes as i32)
+            })
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Toggle method
+    fn toggle(&self) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(|app| app.global::<Parameters>().invoke_toggle())
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Resync method
+    fn resync(&self) -> fdo::Result<()> {
+        let app_weak = self.app_weak.lock().expect("rust: unlock app weak");
+        app_weak
+            .upgrade_in_event_loop(|app| app.global::<Parameters>().invoke_resync())
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Visible property
+    #[dbus_interface(property)]
+    fn visible(&self) -> bool {
+        self.window.visible
+    }
+
+    /// Opacity property
+    #[dbus_interface(property)]
+    fn opacity(&self) -> f64 {
+        self.window.opacity
+    }
+
+    /// Pinned property
+    #[dbus_interface(property)]
+    fn pinned(&self) -> bool {
+        self.window.pinned
+    }
+}
+
+// an applet for one output adds the output to its name, e.g. rs.wl-gammarelay-applet.DP_1.
+pub fn service_name(output: Option<&str>) -> String {
+    match output {
+        Some(output) => format!("{APPLET_SERVICE}.{output}"),
+        None => APPLET_SERVICE.to_string(),
+    }
+}
+
+// call a method on the applet that is already running, if there is one.
+// returns whether one was found.
+pub fn call_running(
+    connection: &zbus::blocking::Connection,
+    output: Option<&str>,
+    method: &str,
+    body: &(impl serde::Serialize + zbus::zvariant::DynamicType),
+) -> zbus::Result<bool> {
+    let service = service_name(output);
+    let name = WellKnownName::try_from(service.as_str())?;
+    if !DBusProxy::new(connection)?.name_has_owner(name.into())? {
+        return Ok(false);
+    }
+    connection.call_method(
+        Some(service.as_str()),
+        APPLET_PATH,
+        Some(APPLET_INTERFACE),
+        method,
+        body,
+    )?;
+    Ok(true)
+}
+
+// request the applet's well-known name and serve the interface on it.
+// the returned connection must be kept alive for as long as the interface is served.
+pub async fn serve(
+    app_weak: Arc<Mutex<slint::Weak<WlGammaRelayApplet>>>,
+    output: Option<&str>,
+) -> zbus::Result<Connection> {
+    ConnectionBuilder::session()?
+        .name(service_name(output))?
+        .serve_at(
+            APPLET_PATH,
+            AppletInterface {
+                app_weak,
+                window: WindowState::default(),
+            },
+        )?
+        .build()
+        .await
+}
+
+// set the window properties of the interface to each state received, emitting
+// PropertiesChanged for those that changed, until the sender is dropped.
+pub async fn publish_window(
+    connection: Connection,
+    mut states: UnboundedReceiver<WindowState>,
+) -> zbus::Result<()> {
+    let interface = connection
+        .object_server()
+        .interface::<_, AppletInterface>(APPLET_PATH)
+        .await?;
+    let context = interface.signal_context();
+    while let Some(state) = states.recv().await {
+        let mut applet = interface.get_mut().await;
+        let before = std::mem::replace(&mut applet.window, state);
+        if before.visible != state.visible {
+            applet.visible_changed(context).await?;
+        }
+        if before.opacity != state.opacity {
+            applet.opacity_changed(context).await?;
+        }
+        if before.pinned != state.pinned {
+            applet.pinned_changed(context).await?;
+        }
+    }
+    Ok(())
+}
@@ -1,13 +1,69 @@
 use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
     sync::{Arc, Mutex},
     thread::spawn,
 };
 
-use clap::Parser;
+use backlight::Backlight;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use commands::FormatMode;
+use config::{
+    check_range, ButtonAction, Config, Link, Preset, Rounding, Steps, Strength, BRIGHTNESS_RANGE,
+    GAMMA_RANGE, TEMPERATURE_RANGE,
+};
+use convert::{
+    backlight_to_string, dbus_brightness_rounded, dbus_brightness_to_string,
+    dbus_brightness_to_ui_value, dbus_gamma_rounded, dbus_gamma_to_string, dbus_gamma_to_ui_value,
+    dbus_temperature_to_string, dbus_temperature_to_ui_value, ddc_to_string, exact_value_to_string,
+    strength_to_string, ui_gamma_to_dbus_value, ui_temperature_to_dbus_value,
+};
+use daemon::{Capabilities, DAEMON_SERVICE};
+use ddc::DdcDisplay;
+use history::History;
+use pending::{Deltas, Op};
+use restore::{Restore, RestoreMode};
+use serde::Serialize;
+use slint::Model;
 use thiserror::Error;
+use worker::{DaemonState, Update, Worker};
 use zbus::{blocking::Connection, dbus_proxy};
 
-slint::include_modules!();
+mod backlight;
+mod bench;
+mod clipboard;
+mod clock;
+mod commands;
+mod compositor;
+mod config;
+mod convert;
+mod daemon;
+mod ddc;
+mod dock;
+mod history;
+mod inhibit;
+mod ipc;
+mod notify;
+mod osd;
+mod pending;
+mod record;
+mod restore;
+mod sandbox;
+mod screencopy;
+mod seat;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod toplevel;
+mod wayland;
+mod worker;
+
+mod ui {
+    // generated code from slint-build trips lints we do not control.
+    #![allow(dead_code, non_local_definitions, unused_imports)]
+    slint::include_modules!();
+}
+use ui::*;
 
 #[derive(Error, Debug)]
 pub enum AppletError {
@@ -17,6 +73,66 @@ pub enum AppletError {
     #[error("zbus::Error")]
     Zbus(#[from] zbus::Error),
 
+    #[error("zbus::fdo::Error")]
+    ZbusFdo(#[from] zbus::fdo::Error),
+
+    #[error("std::io::Error")]
+    Io(#[from] std::io::Error),
+
+    #[error("toml::de::Error")]
+    TomlDe(#[from] toml::de::Error),
+
+    #[error("toml::ser::Error")]
+    TomlSer(#[from] toml::ser::Error),
+
+    #[error("toml_edit::TomlError")]
+    TomlEdit(#[from] toml_edit::TomlError),
+
+    #[error("ddcutil: {0}")]
+    Ddc(String),
+
+    #[error("compositor ipc: {0}")]
+    Compositor(String),
+
+    #[error("wayland_client::ConnectError")]
+    WaylandConnect(#[from] wayland_client::ConnectError),
+
+    #[error("wayland_client::globals::GlobalError")]
+    WaylandGlobal(#[from] wayland_client::globals::GlobalError),
+
+    #[error("wayland_client::globals::BindError")]
+    WaylandBind(#[from] wayland_client::globals::BindError),
+
+    #[error("wayland_client::DispatchError")]
+    WaylandDispatch(#[from] wayland_client::DispatchError),
+
+    #[error("seat {0}")]
+    Seat(String),
+
+    #[error("clipboard: {0}")]
+    Clipboard(String),
+
+    #[error("recording {0}")]
+    Recording(String),
+
+    #[error("environment check failed")]
+    Unhealthy,
+
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+
+    #[error("daemon output not found: {0}")]
+    UnknownOutput(String),
+
+    #[error("daemon does not support per-output control")]
+    NoOutputs,
+
+    #[error("daemon does not support {0}")]
+    Unsupported(&'static str),
+
+    #[error("daemon {DAEMON_SERVICE} is not running")]
+    NoDaemon,
+
     #[error("tokio::task::JoinError")]
     TokioTaskJoin(#[from] tokio::task::JoinError),
 
@@ -24,9 +140,36 @@ pub enum AppletError {
     Unknown,
 }
 
-#[derive(Parser)]
+impl AppletError {
+    // exit codes for scripts to branch on. bad flags count as bad config.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::NoDaemon => 2,
+            Self::SlintPlatform(_) | Self::WaylandConnect(_) => 3,
+            Self::InvalidConfig(_) | Self::TomlDe(_) => 4,
+            _ => 1,
+        }
+    }
+
+    // the error and what caused it, on one line.
+    fn message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(error) = source {
+            message = format!("{message}: {error}");
+            source = error.source();
+        }
+        message.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[derive(Parser, Serialize)]
 #[command(author, version, about, long_about = None)]
+#[serde(rename_all = "kebab-case")]
 struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
     /// Hides switch that controls Invert
     #[arg(short = 'i', long, default_value_t = false)]
     hide_invert: bool,
@@ -48,9 +191,36 @@ struct Args {
     /// Hides text value of active control widgets
     #[arg(short = 'v', long, default_value_t = false)]
     hide_value: bool,
+    /// Shows brightness as 0.00 - 1.00, as the daemon reports it, instead of percent
+    #[arg(long, default_value_t = false)]
+    raw_brightness: bool,
     /// Set this flag to never automatically fade the window.
     #[arg(short = 'f', long, default_value_t = false)]
     never_fade: bool,
+    /// Shows the values as they change but takes no input, e.g. as a status display or in a kiosk
+    #[arg(long, default_value_t = false)]
+    read_only: bool,
+    /// Milliseconds the pointer may be outside the window before it fades out
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    fade_grace_ms: u32,
+    /// How long to wait for the daemon to appear on the bus before exiting with code 2
+    #[arg(long, value_name = "DURATION", default_value = "5s", value_parser = seconds_arg)]
+    startup_timeout: u32,
+    /// Reverts a change by hand larger than the [guard] jump sizes after this long, e.g. 15s, unless the applet is used again
+    #[arg(long, value_name = "DURATION", value_parser = seconds_arg)]
+    safety_revert: Option<u32>,
+    /// Disables the fade and slider animations. Also on when the desktop asks for reduced motion
+    #[arg(long, default_value_t = false)]
+    no_animations: bool,
+    /// Font family of all text, e.g. "JetBrainsMono Nerd Font" [default: the toolkit's]
+    #[arg(long, value_name = "FAMILY")]
+    font: Option<String>,
+    /// Font size of the value text in pixels; other text scales along
+    #[arg(long, value_name = "PX", default_value_t = 12, value_parser = clap::value_parser!(u16).range(4..=72))]
+    font_size: u16,
+    /// Color of the outline around the slider arrow keys step, as #rrggbb or #rrggbbaa
+    #[arg(long, value_name = "COLOR", default_value = "#66aaff", value_parser = color_arg)]
+    highlight_color: String,
     /// Set applet window outer padding
     #[arg(short = 'p', long, default_value_t = 8)]
     outer_padding: usize,
@@ -61,14 +231,243 @@ struct Args {
     #[arg(short = 'y', long, default_value_t = 220)]
     window_height: usize,
     /// 'Reset' value for temperature. (1000 - 10000)
-    #[arg(short = 'T', long, default_value_t = 6500)]
+    #[arg(short = 'T', long, default_value_t = 6500, value_parser = temperature_arg)]
     default_temperature: i16,
     /// 'Reset' value for brightness. (0.0 - 1.0)
-    #[arg(short = 'B', long, default_value_t = 1.0)]
+    #[arg(short = 'B', long, default_value_t = 1.0, value_parser = brightness_arg)]
     default_brightness: f64,
     /// 'Reset' value for gamma. ( 0.5 - 1.5)
-    #[arg(short = 'G', long, default_value_t = 1.0)]
+    #[arg(short = 'G', long, default_value_t = 1.0, value_parser = gamma_arg)]
     default_gamma: f64,
+    /// Shows slider that controls the display backlight via logind, if a backlight device exists
+    #[arg(long, default_value_t = false)]
+    backlight: bool,
+    /// Shows sliders that control external monitor brightness via DDC/CI (requires ddcutil)
+    #[arg(long, default_value_t = false)]
+    ddc: bool,
+    /// Shows one night light strength slider in place of the temperature and brightness sliders. Its endpoints are set in the config file
+    #[arg(long, default_value_t = false)]
+    strength: bool,
+    /// Shows + and − buttons beside each slider. Step sizes are set in the config file
+    #[arg(long, default_value_t = false)]
+    step_buttons: bool,
+    /// Polls the daemon every MS milliseconds for changes made elsewhere, until it is seen to emit change signals
+    #[arg(long, value_name = "MS")]
+    poll_interval: Option<u64>,
+    /// Interval of the update and animation loop, in milliseconds. Higher values use less power
+    #[arg(long, value_name = "MS", default_value_t = TICK_DELTA, value_parser = clap::value_parser!(u64).range(1..))]
+    tick_ms: u64,
+    /// Controls only the named output (see `status`), if the daemon supports per-output control
+    #[arg(long, value_name = "NAME")]
+    output: Option<String>,
+    /// Opens one applet per daemon output, each controlling its own output
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    per_output: bool,
+    /// Applies the next preset in config file order and exits, through the running applet if there is one
+    #[arg(long, default_value_t = false, conflicts_with = "per_output")]
+    cycle_preset: bool,
+    /// Switches gamma between the two [gamma_toggle] values and exits, through the running applet if there is one
+    #[arg(long, default_value_t = false, conflicts_with_all = ["per_output", "cycle_preset"])]
+    toggle_gamma: bool,
+    /// Keeps running and prints a line each time the daemon values change, e.g. "{temperature}K {brightness_pct}%". Also {brightness}, {gamma}, {inverted}
+    #[arg(long, value_name = "FORMAT", conflicts_with = "per_output")]
+    watch: Option<String>,
+    /// Wraps --watch lines for a status bar: i3status (i3status-rust JSON) or polybar (with click and scroll actions)
+    #[arg(long, value_name = "MODE", value_enum, default_value_t = FormatMode::Plain, requires = "watch")]
+    format_mode: FormatMode,
+    /// Keeps running in the background when the window closes, starting hidden. If an applet is already running, toggles its window instead
+    #[arg(long, default_value_t = false, conflicts_with = "per_output")]
+    daemonize: bool,
+    /// Shows changes made while the window is hidden, e.g. by hotkeys, in a small overlay for a second (requires wlr-layer-shell)
+    #[arg(long, default_value_t = false, requires = "daemonize")]
+    osd: bool,
+    /// Keeps a slim strip with a temperature bar and an invert dot on a screen edge, which opens the window when clicked (requires wlr-layer-shell)
+    #[arg(long, value_name = "EDGE", value_enum, requires = "daemonize")]
+    dock: Option<dock::Edge>,
+    /// Moves the window to the output with focus when it opens (Sway and Hyprland)
+    #[arg(long, default_value_t = false, conflicts_with = "per_output")]
+    focused_output: bool,
+    /// Restores the daemon values found at startup when the applet exits, crashes or is killed. With =defaults, resets to the --default-* values instead
+    #[arg(long, value_name = "VALUES", value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "startup")]
+    restore_on_exit: Option<RestoreMode>,
+    /// Prints the final values as KEY=VALUE lines when the window closes, e.g. for `eval` in a wrapper script
+    #[arg(long, default_value_t = false)]
+    print_on_exit: bool,
+    /// Writes the daemon values to FILE each time they change, with the time, while the applet runs
+    #[arg(long, value_name = "FILE", conflicts_with = "per_output")]
+    record: Option<std::path::PathBuf>,
+    /// Writes the values of a --record file to the daemon at the times they were recorded, then exits
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["per_output", "record", "watch"])]
+    replay: Option<std::path::PathBuf>,
+    /// Applies a preset for a while, then restores what it replaced, e.g. night:90m or night:1h30m. Through the running applet if there is one
+    #[arg(long, value_name = "PRESET:DURATION", value_parser = temporary_arg)]
+    temporary: Option<Temporary>,
+    /// When slider moves are written: continuous, or on-release when the slider is let go, e.g. for slow daemons or remote sessions
+    #[arg(long, value_name = "WHEN", value_enum, default_value_t = Commit::Continuous)]
+    commit: Commit,
+    /// What happens to slider moves not yet written when the values change elsewhere, e.g. by a bar module: rebase them onto the new values, or drop them with a hint
+    #[arg(long, value_name = "HOW", value_enum, default_value_t = Reconcile::Rebase)]
+    on_external: Reconcile,
+    /// Runs host tools (swaymsg, hyprctl, ddcutil) through flatpak-spawn and writes files for the host, as inside Flatpak, where it is on by default. See `doctor`
+    #[arg(long, default_value_t = false)]
+    sandbox: bool,
+    /// Controls the daemon of the active session on this seat, e.g. seat1 on a multi-seat system. The session must be your own
+    #[arg(long, value_name = "SEAT")]
+    seat: Option<String>,
+    /// Times N ticks of the slider and daemon pipeline against a mock daemon, then exits
+    #[arg(long, value_name = "N", hide = true, value_parser = clap::value_parser!(u32).range(1..))]
+    bench_ticks: Option<u32>,
+    /// Path to config file [default: $XDG_CONFIG_HOME/wl-gammarelay-applet/config.toml]
+    #[arg(short = 'C', long)]
+    config: Option<std::path::PathBuf>,
+}
+
+// every flag can also be set in the environment, e.g. --fade-grace-ms as
+// WL_GAMMARELAY_APPLET_FADE_GRACE_MS, to vary settings per session.
+const ENV_PREFIX: &str = "WL_GAMMARELAY_APPLET_";
+
+fn args_command() -> clap::Command {
+    Args::command().mut_args(|arg| {
+        let name = format!("{ENV_PREFIX}{}", arg.get_id().as_str().to_uppercase());
+        arg.env(name).hide_env(true)
+    })
+}
+
+fn parse_args() -> Args {
+    let matches = args_command()
+        .try_get_matches()
+        .unwrap_or_else(|e| exit_with(e));
+    Args::from_arg_matches(&matches).unwrap_or_else(|e| exit_with(e))
+}
+
+// help and version go to stdout as usual; usage errors exit as bad config does.
+fn exit_with(error: clap::Error) -> ! {
+    if !error.use_stderr() {
+        error.exit();
+    }
+    let _ = error.print();
+    std::process::exit(4);
+}
+
+// value parsers for flags the daemon limits, naming the allowed range in errors.
+fn temperature_arg(text: &str) -> Result<i16, String> {
+    let value: u16 = text.parse().map_err(|e| format!("{e}"))?;
+    check_range("temperature", value, &TEMPERATURE_RANGE)?;
+    Ok(value as i16)
+}
+
+fn brightness_arg(text: &str) -> Result<f64, String> {
+    let value: f64 = text.parse().map_err(|e| format!("{e}"))?;
+    check_range("brightness", value, &BRIGHTNESS_RANGE)?;
+    Ok(value)
+}
+
+fn gamma_arg(text: &str) -> Result<f64, String> {
+    let value: f64 = text.parse().map_err(|e| format!("{e}"))?;
+    check_range("gamma", value, &GAMMA_RANGE)?;
+    Ok(value)
+}
+
+// a duration like 15s, 2m or 30, in seconds.
+fn seconds_arg(text: &str) -> Result<u32, String> {
+    let invalid = || format!("\"{text}\" is not like \"15s\" or \"2m\"");
+    let (number, unit) = match text.strip_suffix('m') {
+        Some(minutes) => (minutes, 60),
+        None => (text.strip_suffix('s').unwrap_or(text), 1),
+    };
+    let seconds = number
+        .parse::<u32>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(invalid)?;
+    check_range("duration in seconds", seconds, &(1..=600))?;
+    Ok(seconds)
+}
+
+// a color given as #rrggbb or #rrggbbaa.
+fn hex_color(text: &str) -> Option<slint::Color> {
+    let hex = text.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let alpha = if hex.len() == 8 { byte(6)? } else { 255 };
+    Some(slint::Color::from_argb_u8(
+        alpha,
+        byte(0)?,
+        byte(2)?,
+        byte(4)?,
+    ))
+}
+
+fn color_arg(text: &str) -> Result<String, String> {
+    hex_color(text)
+        .map(|_| text.to_string())
+        .ok_or_else(|| format!("\"{text}\" is not like \"#66aaff\""))
+}
+
+// a preset applied for a while, given as NAME:DURATION.
+#[derive(Clone, Serialize)]
+struct Temporary {
+    preset: String,
+    minutes: u32,
+}
+
+// a duration like 90m, 2h, 1h30m or 45, in minutes.
+fn temporary_arg(text: &str) -> Result<Temporary, String> {
+    let invalid = || format!("\"{text}\" is not like \"night:90m\"");
+    let (preset, duration) = text.split_once(':').ok_or_else(invalid)?;
+    let (hours, minutes) = duration.split_once('h').unwrap_or(("0", duration));
+    let minutes = minutes.strip_suffix('m').unwrap_or(minutes);
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = match minutes {
+        "" => 0,
+        minutes => minutes.parse().map_err(|_| invalid())?,
+    };
+    let minutes = hours
+        .checked_mul(60)
+        .and_then(|hours| hours.checked_add(minutes))
+        .ok_or_else(invalid)?;
+    check_range("duration in minutes", minutes, &(1..=1440))?;
+    if preset.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Temporary {
+        preset: preset.to_string(),
+        minutes,
+    })
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints current daemon values and detected daemon capabilities
+    Status,
+    /// Lists the outputs --output accepts, with their current values
+    Outputs,
+    /// Checks the session bus, daemon, and compositor, and suggests fixes
+    Doctor,
+    /// Installs a DBus activation file that starts the applet in the background on demand
+    InstallService {
+        /// Also installs a systemd user unit, which the activation file starts
+        #[arg(long, default_value_t = false)]
+        systemd: bool,
+        /// Orders the systemd unit after this one, e.g. wl-gammarelay-rs.service, and pulls it in
+        #[arg(long, value_name = "UNIT", requires = "systemd")]
+        after: Option<String>,
+    },
+    /// Removes the files install-service wrote
+    UninstallService,
+    /// Inspects the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Prints the effective configuration as TOML: defaults, then the config file, with the flags given before `config` as comments
+    Dump,
 }
 
 // # DBus interface proxy for: `rs.wl.gammarelay`
@@ -94,6 +493,11 @@ trait GammaRelay {
     /// Gamma property
     #[dbus_proxy(property)]
     fn gamma(&self) -> zbus::Result<f64>;
+    /// Grayscale property, on daemons with a grayscale filter
+    #[dbus_proxy(property)]
+    fn grayscale(&self) -> zbus::Result<bool>;
+    #[dbus_proxy(property)]
+    fn set_grayscale(&self, value: bool) -> zbus::Result<()>;
     /// Inverted property
     #[dbus_proxy(property)]
     fn inverted(&self) -> zbus::Result<bool>;
@@ -102,63 +506,94 @@ trait GammaRelay {
     fn temperature(&self) -> zbus::Result<u16>;
 }
 
-// remapping values from low1-high1 to low2-high2 is done like
-// low2 + (value - low1) * (high2 - low2) / (high1 - low1)
-
-// 1000 - 10000 to
-//  0.0 - 1.0
-fn dbus_temperature_to_ui_value(dbus_value: u16) -> f64 {
-    (dbus_value as f64 - 1000.0) * (1.0 / 9000.0)
-}
-fn dbus_temperature_delta_to_ui_value(dbus_value: i16) -> f64 {
-    (dbus_value as f64) * (1.0 / 9000.0)
-}
-fn ui_temperature_delta_to_dbus_value(ui_value: f64) -> i16 {
-    (ui_value * 9000.0) as i16
-}
-fn dbus_temperature_to_string(dbus_value: i16) -> String {
-    format!("{dbus_value} K")
-}
-fn dbus_temperature_rounded(dbus_value: i16) -> i16 {
-    ((dbus_value as f64 / 100.0) as i16) * 100
+// create a proxy for the daemon's root object, or for one of its outputs.
+fn create_proxy(output: Option<&str>) -> Result<GammaRelayProxyBlocking<'static>, AppletError> {
+    let connection = spawn(|| {
+        let connection = seat::daemon_connection()?;
+        daemon::wait_for_daemon(&connection)?;
+        Ok::<_, AppletError>(connection)
+    })
+    .join()
+    .expect("rust: create zbus connection")?;
+    let arc_connection = Arc::new(Mutex::new(connection));
+    let path = output.map(|output| format!("/outputs/{output}"));
+    let create_proxy = spawn(move || {
+        let locked_connection = arc_connection.lock().unwrap();
+        match path {
+            Some(path) => GammaRelayProxyBlocking::builder(&locked_connection)
+                .path(path)?
+                .build(),
+            None => GammaRelayProxyBlocking::new(&locked_connection),
+        }
+    });
+    Ok(create_proxy.join().expect("rust: export proxy")?)
 }
 
-fn dbus_brightness_to_ui_value(dbus_value: f64) -> f64 {
-    dbus_value
-}
-fn ui_brightness_delta_to_dbus_value(ui_value: f64) -> f64 {
-    ui_value
-}
-fn dbus_brightness_to_string(dbus_value: f64) -> String {
-    let percentage = dbus_value * 100.0;
-    format!("{percentage:3.0} %")
-}
-fn dbus_brightness_rounded(dbus_value: f64) -> f64 {
-    (dbus_value * 100.0).round() / 100.0
-}
+// how long the daemon may take to make the object of a connected monitor.
+const HOTPLUG_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
 
-// 0.5 - 1.5 to
-// 0.0 - 1.0
-fn dbus_gamma_to_ui_value(dbus_value: f64) -> f64 {
-    dbus_value - 0.5
-}
-fn dbus_gamma_to_string(dbus_value: f64) -> String {
-    format!("{dbus_value:.2} γ")
-}
-fn dbus_gamma_rounded(dbus_value: f64) -> f64 {
-    (dbus_value * 100.0).round() / 100.0
-}
+// run one applet per daemon output, passing on the other arguments. monitors
+// connected later get an applet too, and those of disconnected ones are closed.
+fn spawn_per_output() -> Result<(), AppletError> {
+    let detect_outputs = || -> Result<Vec<String>, AppletError> {
+        Capabilities::detect(&create_proxy(None)?)?
+            .outputs
+            .filter(|outputs| !outputs.is_empty())
+            .ok_or(AppletError::NoOutputs)
+    };
+    let exe = std::env::current_exe()?;
+    let args: Vec<_> = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--per-output")
+        .collect();
+    let spawn_applet = |output: &str| {
+        std::process::Command::new(&exe)
+            .args(&args)
+            .arg("--output")
+            .arg(output)
+            // an applet per output from the environment would spawn applets in turn.
+            .env_remove(format!("{ENV_PREFIX}PER_OUTPUT"))
+            .spawn()
+    };
+    let mut children = std::collections::HashMap::new();
+    for output in detect_outputs()? {
+        let child = spawn_applet(&output)?;
+        children.insert(output, child);
+    }
 
-fn create_proxy() -> Result<GammaRelayProxyBlocking<'static>, AppletError> {
-    let connection = spawn(|| Connection::session().expect("rust: create zbus connection"))
-        .join()
-        .expect("rust: create zbus connection");
-    let arc_connection = Arc::new(Mutex::new(connection));
-    let create_proxy = spawn(move || {
-        let locked_connection = arc_connection.lock().unwrap();
-        GammaRelayProxyBlocking::new(&locked_connection).expect("rust: create zbus connection")
+    let (sender, hotplug) = std::sync::mpsc::channel();
+    spawn(move || {
+        let result = wayland::watch_outputs(|name, connected| {
+            let _ = sender.send((name.to_string(), connected));
+        });
+        if let Err(e) = result {
+            eprintln!("rust: watch outputs: {e}");
+        }
     });
-    Ok(create_proxy.join().expect("rust: export proxy"))
+    while !children.is_empty() {
+        match hotplug.recv_timeout(HOTPLUG_DELAY) {
+            Ok((name, true)) => {
+                std::thread::sleep(HOTPLUG_DELAY);
+                if !children.contains_key(&name) && detect_outputs()?.contains(&name) {
+                    let child = spawn_applet(&name)?;
+                    children.insert(name, child);
+                }
+            }
+            Ok((name, false)) => {
+                if let Some(mut child) = children.remove(&name) {
+                    child.kill()?;
+                    child.wait()?;
+                }
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            // without a watcher, only wait for the applets to close.
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(HOTPLUG_DELAY)
+            }
+        }
+        children.retain(|_, child| !matches!(child.try_wait(), Ok(Some(_))));
+    }
+    Ok(())
 }
 
 #[derive(Default, Clone, Copy)]
@@ -168,24 +603,85 @@ struct SettingState {
     default: f64,
 }
 
+// when slider moves are written to the daemon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Commit {
+    // as the slider moves.
+    #[default]
+    Continuous,
+    // once the slider is let go; until then only the value text follows it.
+    OnRelease,
+}
+
+// what happens to slider moves not yet written when the daemon values are
+// changed by something else.
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Reconcile {
+    // apply them on top of the new values, as if made after the change.
+    #[default]
+    Rebase,
+    // keep the new values and say the moves were dropped.
+    Drop,
+}
+
 struct Settings {
-    invert: SettingState,
     temperature: SettingState,
     brightness: SettingState,
     gamma: SettingState,
+    backlight: SettingState,
+    rounding: Rounding,
+    // how long sliders must be still before their moves are written.
+    settle: std::time::Duration,
+    slider_moved: Option<std::time::Instant>,
+    commit: Commit,
+    reconcile: Reconcile,
+    // whether a slider is grabbed.
+    held: bool,
+    raw_brightness: bool,
+    link: Link,
+    strength: Strength,
+    // daemon values as of the last write queued; the worker's updates catch it up.
+    daemon: DaemonState,
+    worker: Worker,
+    // sequence number of the deltas request the worker has not yet answered.
+    deltas_in_flight: Option<u64>,
+    deltas_sent: Deltas,
+    // the part of the last deltas request too small to apply; it is not
+    // sent again until the sliders move.
+    deltas_unapplied: Deltas,
+    // sequence number of a requested refresh the ui has not yet snapped to.
+    resync_pending: Option<u64>,
 }
 
 impl Settings {
     fn invalidate_deltas(&mut self) {
-        self.invert.delta_accumulation = 0.0;
         self.temperature.delta_accumulation = 0.0;
         self.brightness.delta_accumulation = 0.0;
         self.gamma.delta_accumulation = 0.0;
+        self.backlight.delta_accumulation = 0.0;
+        self.deltas_in_flight = None;
+        self.deltas_unapplied = Deltas::default();
+    }
+
+    // whether the sliders have been still long enough for their moves to be written.
+    fn settled(&self) -> bool {
+        self.slider_moved
+            .is_none_or(|moved| moved.elapsed() >= self.settle)
+    }
+
+    // whether slider moves are only shown until the slider is let go.
+    fn previewing(&self) -> bool {
+        self.commit == Commit::OnRelease && self.held
     }
 
-    fn set_invert(&mut self, v: bool) {
-        self.invert.delta_accumulation += 1.0;
-        self.invert.value = if v { 1.0 } else { 0.0 };
+    fn deltas(&self) -> Deltas {
+        Deltas {
+            temperature: self.temperature.delta_accumulation,
+            brightness: self.brightness.delta_accumulation,
+            gamma: self.gamma.delta_accumulation,
+        }
     }
 
     fn set_temperature(&mut self, v: f64) {
@@ -202,223 +698,1439 @@ impl Settings {
         self.gamma.delta_accumulation += v - self.gamma.value;
         self.gamma.value = v;
     }
+
+    fn set_backlight(&mut self, v: f64) {
+        self.backlight.delta_accumulation += v - self.backlight.value;
+        self.backlight.value = v;
+    }
 }
 
 const TICK_DELTA: u64 = 7;
+const IDLE_TICK: u64 = 250;
 
-fn main() -> Result<(), AppletError> {
-    let args = Args::parse();
-    let app = WlGammaRelayApplet::new()?;
-    let proxy = Arc::<Mutex<GammaRelayProxyBlocking<'_>>>::new(Mutex::new(
-        create_proxy().expect("rust: create proxy"),
-    ));
+// the tick timer, slowed down while nothing changes so an open window costs
+// almost nothing. input wakes it up again.
+struct Ticker {
+    timer: slint::Timer,
+    interval: std::time::Duration,
+    idle: Cell<bool>,
+}
 
-    // initialize window state and ui values
-    let settings = {
-        // initialize startup ui parameters based on arguments
-        let default_temperature = args.default_temperature as f64;
-        let default_brightness = args.default_brightness;
-        let default_gamma = args.default_gamma;
+impl Ticker {
+    // restart after the window was hidden. the first tick counts as one interval
+    // rather than the whole time the window was hidden.
+    fn resume(&self) {
+        self.idle.set(true);
+        self.timer.restart();
+        self.wake();
+    }
 
-        app.global::<Startup>().set_show_invert(!(args.hide_invert));
-        app.global::<Startup>()
-            .set_show_temperature(!(args.hide_temperature));
-        app.global::<Startup>()
-            .set_show_brightness(!(args.hide_brightness));
-        app.global::<Startup>().set_show_gamma(!(args.hide_gamma));
-        app.global::<Startup>().set_show_caret(!args.hide_caret);
-        app.global::<Startup>().set_show_labels(!args.hide_labels);
-        app.global::<Startup>().set_show_value(!args.hide_value);
-        app.global::<Startup>().set_never_fade(args.never_fade);
-        app.global::<Startup>()
-            .set_outer_padding(args.outer_padding as i32);
-        app.global::<Startup>()
-            .set_window_height(args.window_height as i32);
-        app.global::<Startup>()
-            .set_window_width(args.window_width as i32);
-        app.global::<Startup>()
-            .set_default_temperature(
-                dbus_temperature_to_ui_value(default_temperature as u16) as f32
-            );
-        app.global::<Startup>()
-            .set_default_brightness(dbus_brightness_to_ui_value(default_brightness) as f32);
-        app.global::<Startup>()
-            .set_default_gamma(dbus_gamma_to_ui_value(default_gamma) as f32);
+    fn wake(&self) {
+        if self.idle.replace(false) {
+            self.timer.set_interval(self.interval);
+        }
+    }
 
-        let proxy_ref = proxy.clone();
-        let get_dbus_state = spawn(move || {
-            let proxy = proxy_ref.lock().expect("rust: unlock proxy");
-            let startup_dbus_invert = proxy.inverted().expect("rust: get inverted from dbus");
-            let startup_dbus_temperature = proxy
-                .temperature()
-                .expect("rust: get temperature from dbus");
-            let startup_dbus_brightness =
-                proxy.brightness().expect("rust: get brightness from dbus");
-            let startup_dbus_gamma = proxy.gamma().expect("rust: get gamma from dbus");
-
-            (
-                if startup_dbus_invert { 1.0 } else { 0.0 },
-                dbus_temperature_to_ui_value(startup_dbus_temperature),
-                dbus_brightness_to_ui_value(startup_dbus_brightness),
-                dbus_gamma_to_ui_value(startup_dbus_gamma),
-                startup_dbus_temperature,
-                startup_dbus_brightness,
-                startup_dbus_gamma,
-            )
-        });
+    fn settle(&self) {
+        if !self.idle.replace(true) {
+            self.timer
+                .set_interval(std::time::Duration::from_millis(IDLE_TICK));
+        }
+    }
+}
 
-        let (
-            startup_inverted,
-            startup_temperature,
-            startup_brightness,
-            startup_gamma,
-            startup_dbus_temperature,
-            startup_dbus_brightness,
-            startup_dbus_gamma,
-        ) = get_dbus_state.join().expect("rust: get dbus state");
-
-        if !args.hide_temperature {
-            app.global::<Parameters>()
-                .set_value_text(dbus_temperature_to_string(startup_dbus_temperature as i16).into());
-        } else if !args.hide_brightness {
-            app.global::<Parameters>()
-                .set_value_text(dbus_brightness_to_string(startup_dbus_brightness).into());
-        } else if !args.hide_gamma {
-            app.global::<Parameters>()
-                .set_value_text(dbus_gamma_to_string(startup_dbus_gamma).into());
-        } else {
-            app.global::<Startup>().set_show_value(false);
+// the sliders that change daemon values, and so enter the undo history.
+fn is_daemon_control(name: &str) -> bool {
+    matches!(name, "temperature" | "brightness" | "gamma" | "strength")
+}
+
+// ddc sliders are named "ddc:<index>"
+fn ddc_index(name: &str) -> Option<usize> {
+    name.strip_prefix("ddc:")?.parse().ok()
+}
+
+// what a mouse button bound in [buttons] does, through the callbacks of the
+// controls it stands for.
+fn run_button_action(app: &WlGammaRelayApplet, action: ButtonAction) {
+    let parameters = app.global::<Parameters>();
+    let startup = app.global::<Startup>();
+    let has_grayscale = startup.get_has_grayscale();
+    let has_invert = startup.get_has_invert();
+    match action {
+        ButtonAction::Reset => {
+            for slot in startup.get_slider_order().iter() {
+                parameters.invoke_slider_default(slot.name);
+            }
+            if has_grayscale {
+                parameters.invoke_effect_changed(0);
+            } else if has_invert {
+                parameters.invoke_invert_changed(false);
+            }
+        }
+        ButtonAction::Invert if has_grayscale => {
+            parameters.invoke_effect_changed(if parameters.get_invert() { 0 } else { 1 });
+        }
+        ButtonAction::Invert if has_invert => {
+            parameters.invoke_invert_changed(!parameters.get_invert());
+        }
+        ButtonAction::Invert => {}
+        ButtonAction::Undo => parameters.invoke_undo(),
+        ButtonAction::Redo => parameters.invoke_redo(),
+        ButtonAction::CyclePreset => parameters.invoke_cycle_preset(),
+        ButtonAction::ToggleGamma => parameters.invoke_toggle_gamma(),
+        ButtonAction::Close => parameters.set_force_exit(true),
+        ButtonAction::Preset(name) => parameters.invoke_apply_preset(name.into()),
+        ButtonAction::Step(name, direction) => {
+            parameters.invoke_slider_step(name.into(), direction)
         }
+    }
+}
 
-        // initialize parameter ui values based on current gammarelay state
-        app.global::<Parameters>()
-            .set_invert(startup_inverted > 0.0);
-        app.global::<Parameters>()
-            .set_temperature(startup_temperature as f32);
-        app.global::<Parameters>()
-            .set_brightness(startup_brightness as f32);
-        app.global::<Parameters>().set_gamma(startup_gamma as f32);
+fn control_visible(app: &WlGammaRelayApplet, name: &str) -> bool {
+    match name {
+        "invert" => app.global::<Startup>().get_show_invert(),
+        "temperature" => app.global::<Startup>().get_show_temperature(),
+        "brightness" => app.global::<Startup>().get_show_brightness(),
+        "gamma" => app.global::<Startup>().get_show_gamma(),
+        "strength" => app.global::<Startup>().get_show_strength(),
+        "backlight" => app.global::<Startup>().get_show_backlight(),
+        "ddc" => app.global::<Startup>().get_show_ddc(),
+        _ => false,
+    }
+}
 
-        Arc::<Mutex<Settings>>::new(Mutex::new(Settings {
-            invert: SettingState {
-                value: startup_inverted,
-                delta_accumulation: 0.0,
-                default: 0.0,
-            },
-            temperature: SettingState {
-                value: startup_temperature,
-                delta_accumulation: 0.0,
-                default: default_temperature,
-            },
-            brightness: SettingState {
-                value: startup_brightness,
-                delta_accumulation: 0.0,
-                default: default_brightness,
-            },
-            gamma: SettingState {
-                value: startup_gamma,
-                delta_accumulation: 0.0,
-                default: default_gamma,
-            },
-        }))
+// lay out visible sliders in configured order, and place the invert switch
+// below them if it is ordered after every visible slider. "ddc" expands to
+// one slider per detected display.
+fn apply_control_order(app: &WlGammaRelayApplet) {
+    let order: Vec<slint::SharedString> = app.global::<Startup>().get_order().iter().collect();
+    let ddc_count = app.global::<Parameters>().get_ddc_values().row_count();
+    let visible_sliders: Vec<&slint::SharedString> = order
+        .iter()
+        .filter(|name| name.as_str() != "invert" && control_visible(app, name))
+        .collect();
+    let slider_order: Vec<SliderSlot> = visible_sliders
+        .iter()
+        .flat_map(|name| match name.as_str() {
+            "ddc" => (0..ddc_count)
+                .map(|index| SliderSlot {
+                    name: format!("ddc:{index}").into(),
+                    index: index as i32,
+                })
+                .collect(),
+            _ => vec![SliderSlot {
+                name: (*name).clone(),
+                index: -1,
+            }],
+        })
+        .collect();
+    let invert_index = order.iter().position(|name| name == "invert");
+    let last_slider_index = order
+        .iter()
+        .rposition(|name| visible_sliders.contains(&name));
+    app.global::<Startup>()
+        .set_invert_below(invert_index > last_slider_index);
+    app.global::<Startup>()
+        .set_slider_order(slint::ModelRc::new(slint::VecModel::from(slider_order)));
+}
+
+// show the value a slider is moved to before it is written, with --commit on-release.
+fn preview_value(app: &WlGammaRelayApplet, settings: &Settings, name: &str, value: f64) {
+    let text = match name {
+        "temperature" => dbus_temperature_to_string(
+            ui_temperature_to_dbus_value(value) as i16,
+            settings.rounding.temperature_display,
+        ),
+        "brightness" => dbus_brightness_to_string(value, settings.raw_brightness),
+        "gamma" => dbus_gamma_to_string(ui_gamma_to_dbus_value(value)),
+        "strength" => strength_to_string(value),
+        _ => return,
     };
+    app.global::<Parameters>().set_value_text(text.into());
+}
 
-    // create tick binding which runs opacity management (slint-side)
-    // and also hides (which destroys) the window when done fading out.
-    {
-        let app_weak = app.as_weak();
-        app.on_tick(move |delta| {
-            let binding = app_weak.unwrap();
-            binding.invoke_manage_opacity(delta);
-            let startup_fade_in = binding.global::<Startup>().get_fade_in();
-            let current_opacity = binding.global::<Parameters>().get_window_opacity();
-            if !startup_fade_in && current_opacity < 0.0 {
-                let _ = binding.window().hide();
-            }
-        });
+// set absolute daemon values: move the ui and settings to the value, invalidate
+// pending deltas, and queue the write on the worker.
+fn apply_temperature(app: &WlGammaRelayApplet, settings: &mut Settings, value: u16) {
+    settings.temperature.value = dbus_temperature_to_ui_value(value);
+    app.global::<Parameters>()
+        .set_temperature(settings.temperature.value as f32);
+    app.global::<Parameters>().set_value_text(
+        dbus_temperature_to_string(value as i16, settings.rounding.temperature_display).into(),
+    );
+    app.global::<Parameters>()
+        .set_strength(settings.strength.at_temperature(value) as f32);
+    settings.invalidate_deltas();
+    settings.daemon.temperature = value;
+    settings.worker.send(Op::Temperature(value));
+}
+
+fn apply_brightness(app: &WlGammaRelayApplet, settings: &mut Settings, value: f64) {
+    settings.brightness.value = dbus_brightness_to_ui_value(value);
+    app.global::<Parameters>()
+        .set_brightness(settings.brightness.value as f32);
+    app.global::<Parameters>()
+        .set_value_text(dbus_brightness_to_string(value, settings.raw_brightness).into());
+    settings.invalidate_deltas();
+    settings.daemon.brightness = value;
+    settings.worker.send(Op::Brightness(value));
+}
+
+fn apply_gamma(app: &WlGammaRelayApplet, settings: &mut Settings, value: f64) {
+    settings.gamma.value = dbus_gamma_to_ui_value(value);
+    app.global::<Parameters>()
+        .set_gamma(settings.gamma.value as f32);
+    app.global::<Parameters>()
+        .set_value_text(dbus_gamma_to_string(value).into());
+    settings.invalidate_deltas();
+    settings.daemon.gamma = value;
+    settings.worker.send(Op::Gamma(value));
+}
+
+// move temperature and brightness together to a point between the strength endpoints.
+fn apply_strength(app: &WlGammaRelayApplet, settings: &mut Settings, value: f64) {
+    let (temperature, brightness) = settings.strength.values_at(value);
+    apply_brightness(app, settings, dbus_brightness_rounded(brightness));
+    apply_temperature(app, settings, temperature);
+    app.global::<Parameters>().set_strength(value as f32);
+    app.global::<Parameters>()
+        .set_value_text(strength_to_string(value).into());
+}
+
+// apply the values a preset sets, leaving the others untouched.
+fn apply_preset(app: &WlGammaRelayApplet, settings: &mut Settings, preset: Preset) {
+    if let Some(gamma) = preset.gamma {
+        apply_gamma(app, settings, gamma);
+    }
+    if let Some(brightness) = preset.brightness {
+        apply_brightness(app, settings, brightness);
+    }
+    if let Some(temperature) = preset.temperature {
+        apply_temperature(app, settings, temperature);
+    }
+    if let Some(inverted) = preset.inverted.filter(|i| *i != settings.daemon.inverted) {
+        settings.daemon.inverted = inverted;
+        settings.worker.send(Op::Invert(inverted));
+        app.global::<Parameters>().set_invert(inverted);
     }
+}
+
+// note a change by hand in the undo history, before it is applied.
+fn record_change(app: &WlGammaRelayApplet, history: &mut History, settings: &Settings) {
+    history.record(settings.daemon);
+    app.global::<Parameters>().set_can_undo(history.can_undo());
+}
+
+// how long extreme values can be looked at before they are reverted.
+const CONFIRM_SECONDS: i32 = 10;
 
-    // on invert toggle widget changed, set the settings...
+#[derive(Clone, Copy)]
+struct Guarded {
+    before: DaemonState,
+    // a large change is kept by any later change by hand, not only by ✓.
+    jump: bool,
+}
+
+// ask to keep a change by hand, and revert it once the countdown runs out: with
+// the guard on, values made extreme, e.g. by a stray scroll to a black screen,
+// and with --safety-revert, large changes, e.g. over a remote session.
+fn guard_changes(app: &WlGammaRelayApplet, shared: &Shared, settings: &Settings) {
+    let Some(before) = shared.history.borrow().current_change() else {
+        return;
+    };
+    if let Some(guarded) = shared.guarded.get() {
+        // a change started since shows someone is there to see it.
+        if guarded.jump && before != guarded.before {
+            keep_values(app, shared);
+        }
+        return;
+    }
+    let guard = shared.config.borrow().guard;
+    // values kept once stay kept while they are adjusted further.
+    let (seconds, jump) = if guard.extreme(&settings.daemon) && !guard.extreme(&before) {
+        (CONFIRM_SECONDS, false)
+    } else if let Some(seconds) = shared
+        .safety_revert
+        .filter(|_| guard.jump(&before, &settings.daemon))
     {
-        let settings_ref = settings.clone();
-        app.global::<Parameters>().on_invert_changed(move |value| {
-            let mut settings = settings_ref.lock().expect("rust: unlock settings");
-            settings.set_invert(value);
-        });
+        (seconds as i32, true)
+    } else {
+        return;
+    };
+    shared.guarded.set(Some(Guarded { before, jump }));
+    app.global::<Parameters>().set_confirm_seconds(seconds);
+    let shared_ref = shared.clone();
+    shared.guard_timer.start(
+        slint::TimerMode::Repeated,
+        std::time::Duration::from_secs(1),
+        move || count_down_guard(&shared_ref),
+    );
+}
+
+fn count_down_guard(shared: &Shared) {
+    let Some(app) = shared
+        .current
+        .lock()
+        .expect("rust: unlock window")
+        .upgrade()
+    else {
+        return;
+    };
+    let seconds = app.global::<Parameters>().get_confirm_seconds() - 1;
+    app.global::<Parameters>()
+        .set_confirm_seconds(seconds.max(0));
+    if seconds > 0 {
+        return;
+    }
+    shared.guard_timer.stop();
+    if let Some(guarded) = shared.guarded.take() {
+        shared.ticker.wake();
+        apply_state(&app, &mut shared.settings.borrow_mut(), guarded.before);
+        shared.history.borrow_mut().end_change();
+        app.global::<Parameters>().set_value_text("reverted".into());
     }
+}
 
-    // on slider widget set to default...
-    {
-        let app_weak = app.as_weak();
-        let proxy_ref = proxy.clone();
-        let settings_ref = settings.clone();
-        app.global::<Parameters>().on_slider_default(move |name| {
-            // compare server value to default value and apply the lossless delta.
-            // also set the settings value and invalidate deltas.
-            let mut settings = settings_ref.lock().expect("rust: unlock settings");
-            let app = app_weak.unwrap();
-            match &*name {
-                "temperature" => {
-                    let proxy = proxy_ref.lock().expect("rust: unlock proxy");
-                    let server_value =
-                        proxy.temperature().expect("rust: get server temperature") as i16;
-                    let hard_delta = settings.temperature.default as i16 - server_value;
-                    proxy
-                        .update_temperature(hard_delta as i16)
-                        .expect("rust: expect set temperature");
-                    settings.set_temperature(dbus_temperature_delta_to_ui_value(
-                        server_value + hard_delta,
-                    ));
-                    app.global::<Parameters>().set_value_text(
-                        dbus_temperature_to_string(settings.temperature.default as i16).into(),
-                    );
-                    settings.invalidate_deltas();
-                }
-                "brightness" => {
-                    let proxy = proxy_ref.lock().expect("rust: unlock proxy");
-                    let server_value = proxy.brightness().expect("rust: get server brightness");
-                    let hard_delta = settings.brightness.default - server_value;
-                    proxy
-                        .update_brightness(hard_delta)
-                        .expect("rust: expect set brightness");
-                    settings.set_brightness(dbus_brightness_to_ui_value(server_value + hard_delta));
-                    app.global::<Parameters>().set_value_text(
-                        dbus_brightness_to_string(settings.brightness.default).into(),
-                    );
-                    settings.invalidate_deltas();
+// end the countdown and leave the values be.
+fn keep_values(app: &WlGammaRelayApplet, shared: &Shared) {
+    shared.guard_timer.stop();
+    shared.guarded.set(None);
+    shared.history.borrow_mut().end_change();
+    app.global::<Parameters>().set_confirm_seconds(0);
+}
+
+// go back to values from the history, writing only those that differ, so
+// controls an older daemon lacks are left alone.
+fn apply_state(app: &WlGammaRelayApplet, settings: &mut Settings, state: DaemonState) {
+    let differs = |a: f64, b: f64| (a - b).abs() > 0.0005;
+    let daemon = settings.daemon;
+    apply_preset(
+        app,
+        settings,
+        Preset {
+            temperature: (state.temperature != daemon.temperature).then_some(state.temperature),
+            brightness: differs(state.brightness, daemon.brightness).then_some(state.brightness),
+            gamma: differs(state.gamma, daemon.gamma).then_some(state.gamma),
+            inverted: Some(state.inverted),
+        },
+    );
+    if state.grayscale != daemon.grayscale {
+        settings.daemon.grayscale = state.grayscale;
+        settings.worker.send(Op::Grayscale(state.grayscale));
+        app.global::<Parameters>().set_grayscale(state.grayscale);
+    }
+}
+
+// the current daemon values of the fields a preset sets.
+fn capture_preset(daemon: &DaemonState, fields: Preset) -> Preset {
+    Preset {
+        temperature: fields.temperature.map(|_| daemon.temperature),
+        brightness: fields
+            .brightness
+            .map(|_| dbus_brightness_rounded(daemon.brightness)),
+        gamma: fields.gamma.map(|_| dbus_gamma_rounded(daemon.gamma)),
+        inverted: fields.inverted.map(|_| daemon.inverted),
+    }
+}
+
+// apply updates from the worker, then send accumulated deltas once the previous
+// batch is answered, so at most one deltas request is in flight.
+// returns whether there was anything to do, or still is.
+fn sync_worker(app: &WlGammaRelayApplet, settings: &mut Settings) -> bool {
+    let mut active = false;
+    while let Some(update) = settings.worker.try_recv() {
+        active = true;
+        match update {
+            Update::Synced {
+                handled,
+                state,
+                consumed,
+            } => {
+                if settings.deltas_in_flight.is_some_and(|seq| seq <= handled) {
+                    settings.deltas_in_flight = None;
+                    settings.temperature.delta_accumulation -= consumed.temperature;
+                    settings.brightness.delta_accumulation -= consumed.brightness;
+                    settings.gamma.delta_accumulation -= consumed.gamma;
+                    settings.deltas_unapplied = Deltas {
+                        temperature: settings.deltas_sent.temperature - consumed.temperature,
+                        brightness: settings.deltas_sent.brightness - consumed.brightness,
+                        gamma: settings.deltas_sent.gamma - consumed.gamma,
+                    };
+                    // temperature wins, as linked brightness follows it.
+                    let text = if consumed.temperature != 0.0 {
+                        Some(dbus_temperature_to_string(
+                            state.temperature as i16,
+                            settings.rounding.temperature_display,
+                        ))
+                    } else if consumed.brightness != 0.0 {
+                        Some(dbus_brightness_to_string(
+                            state.brightness,
+                            settings.raw_brightness,
+                        ))
+                    } else if consumed.gamma != 0.0 {
+                        Some(dbus_gamma_to_string(state.gamma))
+                    } else {
+                        None
+                    };
+                    if let Some(text) = text {
+                        app.global::<Parameters>().set_value_text(text.into());
+                    }
                 }
-                "gamma" => {
-                    let proxy = proxy_ref.lock().expect("rust: unlock proxy");
-                    let server_value = proxy.gamma().expect("rust: get server gamma");
-                    let hard_delta = settings.gamma.default - server_value;
-                    proxy
-                        .update_gamma(hard_delta)
-                        .expect("rust: expect set gamma");
-                    settings.set_gamma(dbus_gamma_to_ui_value(server_value + hard_delta));
-                    app.global::<Parameters>()
-                        .set_value_text(dbus_gamma_to_string(settings.gamma.default).into());
-                    settings.invalidate_deltas();
+                // otherwise newer writes are still queued, and ahead of this state.
+                if handled == settings.worker.sent() {
+                    settings.daemon = state;
+                    if settings.resync_pending.take().is_some() {
+                        snap_to_daemon(app, settings);
+                    }
                 }
-                _ => {}
             }
-        });
+            Update::External(state) => {
+                settings.daemon = state;
+                reconcile_external(app, settings);
+            }
+            Update::Failed { error, retry_in } => show_warning(
+                app,
+                &format!(
+                    "daemon error: {error}; retrying in {:.1} s",
+                    retry_in.as_secs_f64()
+                ),
+            ),
+        }
     }
 
-    // on slider widget changed, set the settings...
+    let deltas = settings.deltas();
+    let waiting = deltas != Deltas::default() && deltas != settings.deltas_unapplied;
+    if settings.deltas_in_flight.is_none()
+        && waiting
+        && settings.settled()
+        && !settings.previewing()
     {
-        let settings_ref = settings.clone();
+        settings.deltas_in_flight = Some(settings.worker.send(Op::Deltas(deltas)));
+        settings.deltas_sent = deltas;
+    }
+
+    active || waiting || settings.deltas_in_flight.is_some() || settings.resync_pending.is_some()
+}
+
+// the daemon values were changed by something else while slider moves may not
+// have been written yet. a deltas request in flight is applied by the worker
+// on top of the new values whatever happens here, as the daemon takes relative
+// updates; the parts too small to apply are forgotten.
+fn reconcile_external(app: &WlGammaRelayApplet, settings: &mut Settings) {
+    settings.temperature.delta_accumulation -= settings.deltas_unapplied.temperature;
+    settings.brightness.delta_accumulation -= settings.deltas_unapplied.brightness;
+    settings.gamma.delta_accumulation -= settings.deltas_unapplied.gamma;
+    settings.deltas_unapplied = Deltas::default();
+    if settings.deltas() == Deltas::default() && settings.deltas_in_flight.is_none() {
+        snap_to_daemon(app, settings);
+        return;
+    }
+    match settings.reconcile {
+        // the sliders show the new values moved by what is still to be written.
+        Reconcile::Rebase => {
+            let daemon = settings.daemon;
+            let rebased = |base: f64, setting: &mut SettingState| {
+                setting.value = (base + setting.delta_accumulation).clamp(0.0, 1.0);
+                setting.value as f32
+            };
+            let parameters = app.global::<Parameters>();
+            parameters.set_invert(daemon.inverted);
+            parameters.set_grayscale(daemon.grayscale);
+            parameters.set_temperature(rebased(
+                dbus_temperature_to_ui_value(daemon.temperature),
+                &mut settings.temperature,
+            ));
+            parameters.set_brightness(rebased(
+                dbus_brightness_to_ui_value(daemon.brightness),
+                &mut settings.brightness,
+            ));
+            parameters.set_gamma(rebased(
+                dbus_gamma_to_ui_value(daemon.gamma),
+                &mut settings.gamma,
+            ));
+            let temperature = ui_temperature_to_dbus_value(settings.temperature.value);
+            parameters.set_strength(settings.strength.at_temperature(temperature) as f32);
+        }
+        // moves in flight still land, so snap again once they have.
+        Reconcile::Drop => {
+            let in_flight = settings.deltas_in_flight;
+            snap_to_daemon(app, settings);
+            settings.resync_pending = in_flight.or(settings.resync_pending);
+            show_warning(app, "values changed elsewhere; slider moves dropped");
+        }
+    }
+}
+
+// write a moved backlight slider. it is set absolutely, so only its own
+// accumulation is cleared; daemon deltas still pending or in flight are kept.
+fn sync_backlight(app: &WlGammaRelayApplet, settings: &mut Settings) {
+    if settings.backlight.delta_accumulation == 0.0 {
+        return;
+    }
+    if app.global::<Startup>().get_backlight_available() {
+        app.global::<Parameters>()
+            .set_value_text(backlight_to_string(settings.backlight.value).into());
+        let value = settings.backlight.value;
+        settings.worker.send(Op::Backlight(value));
+    }
+    settings.backlight.delta_accumulation = 0.0;
+}
+
+// move the ui and settings to the daemon values, dropping pending deltas,
+// e.g. after they drifted apart through errors or external changes.
+fn snap_to_daemon(app: &WlGammaRelayApplet, settings: &mut Settings) {
+    let daemon = settings.daemon;
+    settings.temperature.value = dbus_temperature_to_ui_value(daemon.temperature);
+    settings.brightness.value = dbus_brightness_to_ui_value(daemon.brightness);
+    settings.gamma.value = dbus_gamma_to_ui_value(daemon.gamma);
+    settings.invalidate_deltas();
+    app.global::<Parameters>().set_invert(daemon.inverted);
+    app.global::<Parameters>().set_grayscale(daemon.grayscale);
+    app.global::<Parameters>()
+        .set_temperature(settings.temperature.value as f32);
+    app.global::<Parameters>()
+        .set_brightness(settings.brightness.value as f32);
+    app.global::<Parameters>()
+        .set_gamma(settings.gamma.value as f32);
+    app.global::<Parameters>()
+        .set_strength(settings.strength.at_temperature(daemon.temperature) as f32);
+}
+
+// with the link on, move brightness to the curve value for the current temperature.
+// it is written absolutely, leaving the temperature delta and value text alone.
+fn follow_temperature(app: &WlGammaRelayApplet, settings: &mut Settings) {
+    if !app.global::<Parameters>().get_linked() {
+        return;
+    }
+    let temperature = ui_temperature_to_dbus_value(settings.temperature.value);
+    let Some(brightness) = settings.link.brightness_at(temperature) else {
+        return;
+    };
+    let brightness = dbus_brightness_rounded(brightness.clamp(0.0, 1.0));
+    settings.brightness.value = dbus_brightness_to_ui_value(brightness);
+    app.global::<Parameters>()
+        .set_brightness(settings.brightness.value as f32);
+    settings.daemon.brightness = brightness;
+    settings.worker.send(Op::Brightness(brightness));
+}
+
+// change a daemon value by its configured step, applied absolutely so steps are exact.
+fn apply_step(
+    app: &WlGammaRelayApplet,
+    settings: &mut Settings,
+    name: &str,
+    direction: f64,
+    steps: &Steps,
+) {
+    match name {
+        "temperature" => {
+            let server_value = settings.daemon.temperature as f64;
+            let value =
+                (server_value + direction * steps.temperature as f64).clamp(1000.0, 10000.0);
+            apply_temperature(app, settings, value as u16);
+            follow_temperature(app, settings);
+        }
+        "brightness" => {
+            let server_value = settings.daemon.brightness;
+            let value = dbus_brightness_rounded(server_value + direction * steps.brightness)
+                .clamp(0.2, 1.0);
+            apply_brightness(app, settings, value);
+        }
+        "gamma" => {
+            let server_value = settings.daemon.gamma;
+            let value = dbus_gamma_rounded(server_value + direction * steps.gamma).clamp(0.5, 1.5);
+            apply_gamma(app, settings, value);
+        }
+        _ => (),
+    }
+}
+
+const WARNING_DURATION: u64 = 3000;
+
+// show a transient warning above the controls, e.g. while the daemon restarts.
+fn show_warning(app: &WlGammaRelayApplet, text: &str) {
+    if app.global::<Parameters>().get_warning_text() == text {
+        return;
+    }
+    eprintln!("rust: {text}");
+    app.global::<Parameters>().set_warning_text(text.into());
+    app.global::<Parameters>().set_last_error(text.into());
+    let app_weak = app.as_weak();
+    let text = slint::SharedString::from(text);
+    slint::Timer::single_shot(
+        std::time::Duration::from_millis(WARNING_DURATION),
+        move || {
+            // a newer warning clears itself.
+            if let Some(app) = app_weak.upgrade() {
+                if app.global::<Parameters>().get_warning_text() == text {
+                    app.global::<Parameters>().set_warning_text("".into());
+                }
+            }
+        },
+    );
+}
+
+// what outlives a window. a hidden wayland window stays mapped, invisible but
+// catching input, so background mode replaces it with a fresh one instead.
+#[derive(Clone)]
+struct Shared {
+    settings: Rc<RefCell<Settings>>,
+    config: Rc<RefCell<Config>>,
+    ticker: Rc<Ticker>,
+    night_saved: Rc<RefCell<Option<Preset>>>,
+    // what an override preset replaced, restored once it ends.
+    override_saved: Rc<RefCell<Option<Preset>>>,
+    // values before changes by hand, for undo and redo.
+    history: Rc<RefCell<History>>,
+    // the schedule's values as last applied, so they are only written when they change.
+    schedule_applied: Rc<Cell<Option<Preset>>>,
+    // ends a temporary preset.
+    timer: Rc<slint::Timer>,
+    // the values before a change by hand that must be kept, restored unless it is.
+    guarded: Rc<Cell<Option<Guarded>>>,
+    // counts down the seconds left to keep it.
+    guard_timer: Rc<slint::Timer>,
+    // seconds before a large change by hand is reverted, with --safety-revert.
+    safety_revert: Option<u32>,
+    ddc_displays: Arc<Mutex<Vec<DdcDisplay>>>,
+    // whether the screen is kept from idling, sent while a slider is dragged.
+    idle_inhibit: Option<std::sync::mpsc::Sender<bool>>,
+    // the window's visibility, opacity and pinning, for the dbus interface.
+    window_state: Rc<ipc::WindowPublisher>,
+    // owns the window, which the callbacks only hold weakly.
+    window: Rc<RefCell<Option<WlGammaRelayApplet>>>,
+    // the window for other threads and the applet's dbus interface.
+    current: Arc<Mutex<slint::Weak<WlGammaRelayApplet>>>,
+    focused_output: bool,
+    daemonize: bool,
+}
+
+// build a window with the state of the hidden one, ready to be shown at once.
+fn rebuild_window(shared: &Shared) -> Result<(), AppletError> {
+    let app = WlGammaRelayApplet::new()?;
+    if let Some(old) = shared.window.borrow().as_ref() {
+        carry_over(old, &app);
+    }
+    connect_window(&app, shared);
+    shared.ticker.timer.stop();
+    *shared.current.lock().expect("rust: unlock window") = app.as_weak();
+    *shared.window.borrow_mut() = Some(app);
+    Ok(())
+}
+
+// copy what the window shows and how it is set up, leaving out transient state.
+fn carry_over(from: &WlGammaRelayApplet, to: &WlGammaRelayApplet) {
+    let (from_startup, to_startup) = (from.global::<Startup>(), to.global::<Startup>());
+    to_startup.set_fade_in_speed(from_startup.get_fade_in_speed());
+    to_startup.set_fade_out_speed(from_startup.get_fade_out_speed());
+    to_startup.set_fade_grace_ms(from_startup.get_fade_grace_ms());
+    to_startup.set_animations(from_startup.get_animations());
+    to_startup.set_font_family(from_startup.get_font_family());
+    to_startup.set_font_size(from_startup.get_font_size());
+    to_startup.set_show_value(from_startup.get_show_value());
+    to_startup.set_show_night_mode(from_startup.get_show_night_mode());
+    to_startup.set_show_timer(from_startup.get_show_timer());
+    to_startup.set_timer_preset(from_startup.get_timer_preset());
+    to_startup.set_timer_minutes(from_startup.get_timer_minutes());
+    to_startup.set_show_gamma_toggle(from_startup.get_show_gamma_toggle());
+    to_startup.set_show_link(from_startup.get_show_link());
+    to_startup.set_show_invert(from_startup.get_show_invert());
+    to_startup.set_show_temperature(from_startup.get_show_temperature());
+    to_startup.set_show_brightness(from_startup.get_show_brightness());
+    to_startup.set_show_gamma(from_startup.get_show_gamma());
+    to_startup.set_show_strength(from_startup.get_show_strength());
+    to_startup.set_show_backlight(from_startup.get_show_backlight());
+    to_startup.set_backlight_available(from_startup.get_backlight_available());
+    to_startup.set_show_ddc(from_startup.get_show_ddc());
+    to_startup.set_order(from_startup.get_order());
+    to_startup.set_slider_order(from_startup.get_slider_order());
+    to_startup.set_invert_below(from_startup.get_invert_below());
+    to_startup.set_has_grayscale(from_startup.get_has_grayscale());
+    to_startup.set_has_invert(from_startup.get_has_invert());
+    to_startup.set_show_caret(from_startup.get_show_caret());
+    to_startup.set_show_labels(from_startup.get_show_labels());
+    to_startup.set_show_step_buttons(from_startup.get_show_step_buttons());
+    to_startup.set_highlight_color(from_startup.get_highlight_color());
+    to_startup.set_output(from_startup.get_output());
+    to_startup.set_about_text(from_startup.get_about_text());
+    to_startup.set_never_fade(from_startup.get_never_fade());
+    to_startup.set_read_only(from_startup.get_read_only());
+    to_startup.set_outer_padding(from_startup.get_outer_padding());
+    to_startup.set_window_width(from_startup.get_window_width());
+    to_startup.set_window_height(from_startup.get_window_height());
+    to_startup.set_default_temperature(from_startup.get_default_temperature());
+    to_startup.set_default_brightness(from_startup.get_default_brightness());
+    to_startup.set_default_gamma(from_startup.get_default_gamma());
+    to_startup.set_default_backlight(from_startup.get_default_backlight());
+    to_startup.set_ddc_defaults(from_startup.get_ddc_defaults());
+    to_startup.set_ddc_labels(from_startup.get_ddc_labels());
+
+    let (from_parameters, to_parameters) = (from.global::<Parameters>(), to.global::<Parameters>());
+    to_parameters.set_value_text(from_parameters.get_value_text());
+    to_parameters.set_focus_retain(from_parameters.get_focus_retain());
+    to_parameters.set_night_mode(from_parameters.get_night_mode());
+    to_parameters.set_can_undo(from_parameters.get_can_undo());
+    to_parameters.set_last_error(from_parameters.get_last_error());
+    to_parameters.set_linked(from_parameters.get_linked());
+    to_parameters.set_invert(from_parameters.get_invert());
+    to_parameters.set_grayscale(from_parameters.get_grayscale());
+    to_parameters.set_temperature(from_parameters.get_temperature());
+    to_parameters.set_brightness(from_parameters.get_brightness());
+    to_parameters.set_gamma(from_parameters.get_gamma());
+    to_parameters.set_backlight(from_parameters.get_backlight());
+    to_parameters.set_strength(from_parameters.get_strength());
+    to_parameters.set_ddc_values(from_parameters.get_ddc_values());
+}
+
+// tell the dbus interface how the window looks after a tick.
+fn publish_window_state(app: &WlGammaRelayApplet, publisher: &ipc::WindowPublisher) {
+    let parameters = app.global::<Parameters>();
+    let opacity = parameters.get_window_opacity().clamp(0.0, 1.0) as f64;
+    publisher.publish(ipc::WindowState {
+        visible: app.window().is_visible(),
+        opacity: (opacity * 100.0).round() / 100.0,
+        pinned: app.global::<Startup>().get_never_fade()
+            || parameters.get_focus_retain()
+            || parameters.get_saving_preset()
+            || parameters.get_confirm_seconds() > 0,
+    });
+}
+
+// connect a window's callbacks and the tick to the shared state. background mode
+// calls this again for each rebuilt window.
+fn connect_window(app: &WlGammaRelayApplet, shared: &Shared) {
+    // create tick binding which runs opacity management (slint-side)
+    // and also hides (which destroys) the window when done fading out.
+    {
+        let app_weak = app.as_weak();
+        app.on_tick(move |delta| {
+            let binding = app_weak.unwrap();
+            binding.invoke_manage_opacity(delta);
+            let startup_fade_in = binding.global::<Startup>().get_fade_in();
+            let current_opacity = binding.global::<Parameters>().get_window_opacity();
+            if !startup_fade_in && current_opacity < 0.0 {
+                let _ = binding.window().hide();
+            }
+        });
+    }
+
+    // on invert toggle widget changed, ask the daemon for the requested state.
+    // the switch shows the daemon's bool, which change signals keep current.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        app.global::<Parameters>().on_invert_changed(move |value| {
+            ticker_ref.wake();
+            let mut settings = settings_ref.borrow_mut();
+            if value != settings.daemon.inverted {
+                record_change(&app_weak.unwrap(), &mut history_ref.borrow_mut(), &settings);
+            }
+            settings.daemon.inverted = value;
+            settings.worker.send(Op::Invert(value));
+            app_weak.unwrap().global::<Parameters>().set_invert(value);
+        });
+    }
+
+    // on an effect picked where the daemon has grayscale: normal, inverted or
+    // grayscale, which are not combined.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        app.global::<Parameters>().on_effect_changed(move |effect| {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            let (inverted, grayscale) = (effect == 1, effect == 2);
+            if (inverted, grayscale) != (settings.daemon.inverted, settings.daemon.grayscale) {
+                record_change(&app, &mut history_ref.borrow_mut(), &settings);
+            }
+            settings.daemon.inverted = inverted;
+            settings.daemon.grayscale = grayscale;
+            settings.worker.send(Op::Invert(inverted));
+            settings.worker.send(Op::Grayscale(grayscale));
+            app.global::<Parameters>().set_invert(inverted);
+            app.global::<Parameters>().set_grayscale(grayscale);
+        });
+    }
+
+    // on toggle, hide a shown window, or show a hidden one and resume ticking and
+    // following the daemon. the window fades out on its own otherwise.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let focused_output = shared.focused_output;
+        app.global::<Parameters>().on_toggle(move || {
+            let app = app_weak.unwrap();
+            if app.window().is_visible() {
+                if let Err(e) = app.hide() {
+                    eprintln!("rust: hide window: {e}");
+                }
+                return;
+            }
+            // fade in as on startup, unless the window is meant to stay put.
+            let never_fade = app.global::<Startup>().get_never_fade();
+            app.global::<Parameters>().set_force_exit(false);
+            app.global::<Parameters>()
+                .set_window_opacity(if never_fade { 1.0 } else { 0.0 });
+            app.global::<Parameters>().set_away_ms(0.0);
+            app.global::<Parameters>().set_intro_done(false);
+            app.global::<Startup>().set_fade_in(true);
+            if let Err(e) = app.show() {
+                eprintln!("rust: show window: {e}");
+                return;
+            }
+            settings_ref.borrow().worker.park(false);
+            ticker_ref.resume();
+            if focused_output {
+                spawn(|| {
+                    if let Err(e) = compositor::move_to_focused_output() {
+                        eprintln!("rust: move window to focused output: {e}");
+                    }
+                });
+            }
+        });
+    }
+
+    // on refresh, re-read the daemon once queued writes are done, then snap the ui to it.
+    {
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        app.global::<Parameters>().on_resync(move || {
+            ticker_ref.wake();
+            let mut settings = settings_ref.borrow_mut();
+            let seq = settings.worker.send(Op::Refresh);
+            settings.resync_pending = Some(seq);
+        });
+    }
+
+    // on link toggled, snap brightness to the curve so it follows from here.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        app.global::<Parameters>().on_link_changed(move |linked| {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            app.global::<Parameters>().set_linked(linked);
+            follow_temperature(&app, &mut settings);
+        });
+    }
+
+    // on night mode toggled, apply the night preset, or restore what it replaced.
+    // values changed while night mode was on are kept unless the preset set them.
+    if let Some(night_preset) = shared.config.borrow().night_preset() {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let saved_ref = shared.night_saved.clone();
+        app.global::<Parameters>()
+            .on_night_mode_changed(move |enabled| {
+                ticker_ref.wake();
+                let app = app_weak.unwrap();
+                let mut settings = settings_ref.borrow_mut();
+                record_change(&app, &mut history_ref.borrow_mut(), &settings);
+                let mut saved = saved_ref.borrow_mut();
+                let preset = if enabled {
+                    *saved = Some(capture_preset(&settings.daemon, night_preset));
+                    night_preset
+                } else {
+                    // without saved values (e.g. night mode was on at startup), reset to defaults.
+                    saved.take().unwrap_or(Preset {
+                        temperature: night_preset
+                            .temperature
+                            .map(|_| settings.temperature.default as u16),
+                        brightness: night_preset.brightness.map(|_| settings.brightness.default),
+                        gamma: night_preset.gamma.map(|_| settings.gamma.default),
+                        inverted: night_preset.inverted.map(|_| false),
+                    })
+                };
+                apply_preset(&app, &mut settings, preset);
+                app.global::<Parameters>().set_night_mode(enabled);
+            });
+    }
+
+    app.global::<Parameters>()
+        .on_exact_value(|name, value| exact_value_to_string(&name, value as f64).into());
+
+    // on slider widget set to default...
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let ddc_displays_ref = shared.ddc_displays.clone();
+        app.global::<Parameters>().on_slider_default(move |name| {
+            ticker_ref.wake();
+            // set the settings value, invalidate deltas, and queue the default for the tick.
+            let mut settings = settings_ref.borrow_mut();
+            let app = app_weak.unwrap();
+            if is_daemon_control(&name) {
+                record_change(&app, &mut history_ref.borrow_mut(), &settings);
+            }
+            match &*name {
+                "temperature" => {
+                    let default = settings.temperature.default as u16;
+                    apply_temperature(&app, &mut settings, default);
+                    follow_temperature(&app, &mut settings);
+                }
+                "brightness" => {
+                    let default = settings.brightness.default;
+                    apply_brightness(&app, &mut settings, default);
+                }
+                "gamma" => {
+                    let default = settings.gamma.default;
+                    apply_gamma(&app, &mut settings, default);
+                }
+                "strength" => apply_strength(&app, &mut settings, 0.0),
+                "backlight" => {
+                    // the backlight is set absolutely, so the tick applies the default.
+                    let default = settings.backlight.default;
+                    settings.set_backlight(default);
+                    app.global::<Parameters>().set_backlight(default as f32);
+                }
+                name => {
+                    if let Some(index) = ddc_index(name) {
+                        let mut displays = ddc_displays_ref.lock().expect("rust: unlock ddc");
+                        let default = app.global::<Startup>().get_ddc_defaults().row_data(index);
+                        if let (Some(display), Some(default)) = (displays.get_mut(index), default) {
+                            display.set_value(default as f64);
+                            app.global::<Parameters>()
+                                .get_ddc_values()
+                                .set_row_data(index, default);
+                            app.global::<Parameters>().set_value_text(
+                                ddc_to_string(display.value, &display.label).into(),
+                            );
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // on step button clicked, change the value by the configured step. daemon
+    // values are applied absolutely so steps are exact; others reuse slider-changed.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let steps = shared.config.borrow().step;
+        app.global::<Parameters>()
+            .on_slider_step(move |name, direction| {
+                ticker_ref.wake();
+                let app = app_weak.unwrap();
+                if is_daemon_control(&name) {
+                    record_change(&app, &mut history_ref.borrow_mut(), &settings_ref.borrow());
+                }
+                let direction = direction as f64;
+                match &*name {
+                    "temperature" | "brightness" | "gamma" => {
+                        let mut settings = settings_ref.borrow_mut();
+                        apply_step(&app, &mut settings, &name, direction, &steps);
+                    }
+                    "backlight" => {
+                        let value = app.global::<Parameters>().get_backlight() as f64
+                            + direction * steps.backlight;
+                        app.global::<Parameters>()
+                            .invoke_slider_changed(name, value.clamp(0.0, 1.0) as f32);
+                    }
+                    "strength" => {
+                        let value = app.global::<Parameters>().get_strength() as f64
+                            + direction * steps.strength;
+                        app.global::<Parameters>()
+                            .invoke_slider_changed(name, value.clamp(0.0, 1.0) as f32);
+                    }
+                    name => {
+                        let ddc_values = app.global::<Parameters>().get_ddc_values();
+                        if let Some(current) = ddc_index(name).and_then(|i| ddc_values.row_data(i))
+                        {
+                            let value = current as f64 + direction * steps.ddc;
+                            app.global::<Parameters>()
+                                .invoke_slider_changed(name.into(), value.clamp(0.0, 1.0) as f32);
+                        }
+                    }
+                }
+            });
+    }
+
+    // on save preset, write the daemon values of the shown controls to the config
+    // file under the entered name.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>().on_save_preset(move |name| {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            app.global::<Parameters>().set_saving_preset(false);
+            let name = name.trim();
+            if name.is_empty() {
+                return;
+            }
+            let startup = app.global::<Startup>();
+            let fields = Preset {
+                temperature: startup.get_show_temperature().then_some(0),
+                brightness: startup.get_show_brightness().then_some(0.0),
+                gamma: startup.get_show_gamma().then_some(0.0),
+                inverted: (startup.get_show_invert() && config_ref.borrow().presets_invert)
+                    .then_some(false),
+            };
+            let preset = capture_preset(&settings_ref.borrow().daemon, fields);
+            match config_ref.borrow_mut().save_preset(name, preset) {
+                Ok(()) => app
+                    .global::<Parameters>()
+                    .set_value_text(format!("saved \"{name}\"").into()),
+                Err(e) => show_warning(&app, &format!("save preset: {e}")),
+            }
+        });
+    }
+
+    // on cycle preset, apply the preset after the one the daemon shows.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>().on_cycle_preset(move || {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            record_change(&app, &mut history_ref.borrow_mut(), &settings);
+            let config = config_ref.borrow();
+            match config.next_preset(&settings.daemon) {
+                Some((name, preset)) => {
+                    apply_preset(&app, &mut settings, preset);
+                    app.global::<Parameters>().set_value_text(name.into());
+                }
+                None => show_warning(&app, "no presets to cycle"),
+            }
+        });
+    }
+
+    // on toggle gamma, switch to the other of the two toggle values.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>().on_toggle_gamma(move || {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            record_change(&app, &mut history_ref.borrow_mut(), &settings);
+            let gamma = config_ref.borrow().gamma_toggle.next(settings.daemon.gamma);
+            apply_gamma(&app, &mut settings, gamma);
+        });
+    }
+
+    // on a mouse button or the wheel on the window background.
+    {
+        let app_weak = app.as_weak();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>()
+            .on_background_button(move |button| {
+                let action = config_ref.borrow().buttons.action(&button);
+                if let Some(action) = action {
+                    run_button_action(&app_weak.unwrap(), action);
+                }
+            });
+    }
+
+    // on a preset requested by name, e.g. by the desktop theme or a script.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>().on_apply_preset(move |name| {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let Some(preset) = config_ref.borrow().presets.get(name.as_str()).copied() else {
+                show_warning(&app, &format!("no preset named \"{name}\""));
+                return;
+            };
+            let mut settings = settings_ref.borrow_mut();
+            record_change(&app, &mut history_ref.borrow_mut(), &settings);
+            apply_preset(&app, &mut settings, preset);
+            app.global::<Parameters>().set_value_text(name);
+        });
+    }
+
+    // on an override, e.g. game mode, apply its preset over the current values,
+    // and restore those once it ends.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        let saved_ref = shared.override_saved.clone();
+        app.global::<Parameters>().on_override_preset(move |name| {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            let mut saved = saved_ref.borrow_mut();
+            if name.is_empty() {
+                if let Some(preset) = saved.take() {
+                    apply_preset(&app, &mut settings, preset);
+                }
+                return;
+            }
+            let Some(preset) = config_ref.borrow().preset(name.as_str()) else {
+                show_warning(&app, &format!("no preset named \"{name}\""));
+                return;
+            };
+            // keep the values from before the first override when one follows another.
+            saved.get_or_insert_with(|| capture_preset(&settings.daemon, Preset::NEUTRAL));
+            apply_preset(&app, &mut settings, preset);
+            app.global::<Parameters>().set_value_text(name);
+        });
+    }
+
+    // on a monitor connected, write the values of its profile, or else the
+    // applet's, to it once the daemon has made its object, as the daemon starts
+    // new outputs at its defaults. an applet for one output only looks after that output.
+    {
+        let app_weak = app.as_weak();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        app.global::<Parameters>().on_output_connected(move |name| {
+            let app = app_weak.unwrap();
+            let output = app.global::<Startup>().get_output();
+            if !output.is_empty() && output != name {
+                return;
+            }
+            if let Some(profile) = config_ref.borrow().output_profile(&name) {
+                let values = profile.values();
+                spawn(move || {
+                    std::thread::sleep(HOTPLUG_DELAY);
+                    if let Err(e) = commands::apply_output_profile(&name, values, false) {
+                        eprintln!("rust: apply profile of output {name}: {e}");
+                    }
+                });
+                return;
+            }
+            // only daemons with per-output objects report them, and those
+            // all have invert and gamma.
+            let has_grayscale = app_weak.unwrap().global::<Startup>().get_has_grayscale();
+            let restore = Restore::new(
+                Some(&name),
+                settings_ref.borrow().daemon,
+                true,
+                true,
+                has_grayscale,
+            );
+            spawn(move || {
+                std::thread::sleep(HOTPLUG_DELAY);
+                restore.run();
+            });
+        });
+    }
+
+    // on a temporary preset, apply it as an override until the timer ends it.
+    // the timer outlives the window, so it ends the preset on whichever is current.
+    {
+        let app_weak = app.as_weak();
+        let timer_ref = shared.timer.clone();
+        let config_ref = shared.config.clone();
+        let current = shared.current.clone();
+        app.global::<Parameters>()
+            .on_temporary_preset(move |name, minutes| {
+                let app = app_weak.unwrap();
+                if !name.is_empty() && config_ref.borrow().preset(&name).is_none() {
+                    show_warning(&app, &format!("no preset named \"{name}\""));
+                    return;
+                }
+                if name.is_empty() || minutes <= 0 {
+                    timer_ref.stop();
+                    app.global::<Parameters>().set_timer_active(false);
+                    app.global::<Parameters>().invoke_override_preset("".into());
+                    return;
+                }
+                app.global::<Parameters>()
+                    .invoke_override_preset(name.clone());
+                app.global::<Parameters>().set_timer_active(true);
+                app.global::<Parameters>()
+                    .set_value_text(format!("{name} for {minutes} min").into());
+                let current = current.clone();
+                timer_ref.start(
+                    slint::TimerMode::SingleShot,
+                    std::time::Duration::from_secs(minutes as u64 * 60),
+                    move || {
+                        if let Some(app) = current.lock().expect("rust: unlock window").upgrade() {
+                            app.global::<Parameters>()
+                                .invoke_temporary_preset("".into(), 0);
+                        }
+                    },
+                );
+            });
+    }
+
+    // on keep, end the countdown to reverting a change.
+    {
+        let app_weak = app.as_weak();
+        let shared_ref = shared.clone();
+        app.global::<Parameters>().on_keep_values(move || {
+            keep_values(&app_weak.unwrap(), &shared_ref);
+        });
+    }
+
+    // follow the schedule, writing its values only when they change, so values
+    // set by hand last until the schedule moves on. overrides take precedence.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        let saved_ref = shared.override_saved.clone();
+        let applied_ref = shared.schedule_applied.clone();
+        app.global::<Parameters>().on_schedule_tick(move || {
+            if saved_ref.borrow().is_some() {
+                return;
+            }
+            let Some(preset) = config_ref.borrow().scheduled_preset(clock::local_minute()) else {
+                return;
+            };
+            let preset = Preset {
+                temperature: preset.temperature,
+                brightness: preset.brightness.map(dbus_brightness_rounded),
+                gamma: preset.gamma.map(dbus_gamma_rounded),
+                inverted: preset.inverted,
+            };
+            if applied_ref.replace(Some(preset)) == Some(preset) {
+                return;
+            }
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            apply_preset(&app, &mut settings_ref.borrow_mut(), preset);
+        });
+    }
+
+    // move brightness toward the configured exposure of the sampled content,
+    // unless an override such as game mode holds the values.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let config_ref = shared.config.clone();
+        let saved_ref = shared.override_saved.clone();
+        let adjusting = Cell::new(false);
+        app.global::<Parameters>()
+            .on_content_sampled(move |luminance| {
+                if saved_ref.borrow().is_some() {
+                    return;
+                }
+                let auto_brightness = config_ref.borrow().auto_brightness;
+                let mut settings = settings_ref.borrow_mut();
+                let mut adjusting_now = adjusting.get();
+                let next = auto_brightness.next(
+                    settings.daemon.brightness,
+                    luminance as f64,
+                    &mut adjusting_now,
+                );
+                adjusting.set(adjusting_now);
+                if let Some(brightness) = next {
+                    ticker_ref.wake();
+                    let app = app_weak.unwrap();
+                    apply_brightness(&app, &mut settings, dbus_brightness_rounded(brightness));
+                }
+            });
+    }
+
+    // on copy, put the values of the shown controls on the clipboard as busctl
+    // commands or as a preset. wl-copy runs on a thread, as it may be slow to start.
+    {
+        let app_weak = app.as_weak();
+        let settings_ref = shared.settings.clone();
+        app.global::<Parameters>().on_copy_values(move |format| {
+            let app = app_weak.unwrap();
+            let startup = app.global::<Startup>();
+            let fields = clipboard::Fields {
+                temperature: startup.get_show_temperature() || startup.get_show_strength(),
+                brightness: startup.get_show_brightness() || startup.get_show_strength(),
+                gamma: startup.get_show_gamma(),
+                invert: startup.get_show_invert(),
+            };
+            let state = settings_ref.borrow().daemon;
+            let output = startup.get_output();
+            let output = (!output.is_empty()).then_some(output.as_str());
+            let text = match &*format {
+                "preset" => clipboard::preset_snippet(&state, &fields),
+                _ => Ok(clipboard::busctl_snippet(&state, &fields, output)),
+            };
+            let app_weak = app.as_weak();
+            spawn(move || {
+                let result = text
+                    .and_then(|text| clipboard::copy(&text))
+                    .map_err(|e| e.to_string());
+                let _ = app_weak.upgrade_in_event_loop(move |app| match result {
+                    Ok(()) => app.global::<Parameters>().set_value_text("copied".into()),
+                    Err(e) => show_warning(&app, &format!("copy values: {e}")),
+                });
+            });
+        });
+    }
+
+    // on copy in the about panel, put its text on the clipboard for an issue report.
+    {
+        let app_weak = app.as_weak();
+        app.global::<Parameters>().on_copy_about(move || {
+            let app = app_weak.unwrap();
+            let parameters = app.global::<Parameters>();
+            let last_error = parameters.get_last_error();
+            let text = format!(
+                "{}\nlast error: {}\n",
+                app.global::<Startup>().get_about_text(),
+                if last_error.is_empty() {
+                    "none"
+                } else {
+                    &last_error
+                }
+            );
+            let app_weak = app.as_weak();
+            spawn(move || {
+                let result = clipboard::copy(&text).map_err(|e| e.to_string());
+                let _ = app_weak.upgrade_in_event_loop(move |app| match result {
+                    Ok(()) => app.global::<Parameters>().set_value_text("copied".into()),
+                    Err(e) => show_warning(&app, &format!("copy about: {e}")),
+                });
+            });
+        });
+    }
+
+    // on undo or redo, move through the values from before changes by hand.
+    for redo in [false, true] {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let history_ref = shared.history.clone();
+        let step = move || {
+            ticker_ref.wake();
+            let app = app_weak.unwrap();
+            let mut settings = settings_ref.borrow_mut();
+            let mut history = history_ref.borrow_mut();
+            let current = settings.daemon;
+            let state = if redo {
+                history.redo(current)
+            } else {
+                history.undo(current)
+            };
+            if let Some(state) = state {
+                apply_state(&app, &mut settings, state);
+            }
+            app.global::<Parameters>().set_can_undo(history.can_undo());
+        };
+        if redo {
+            app.global::<Parameters>().on_redo(step);
+        } else {
+            app.global::<Parameters>().on_undo(step);
+        }
+    }
+
+    // on slider widget changed, set the settings...
+    // ddc displays are written directly, as their writes are queued on their own threads.
+    {
+        let app_weak = app.as_weak();
+        let history_ref = shared.history.clone();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let ddc_displays_ref = shared.ddc_displays.clone();
         app.global::<Parameters>()
             .on_slider_changed(move |name, value| {
-                let mut settings = settings_ref.lock().expect("rust: unlock settings");
+                ticker_ref.wake();
+                let app = app_weak.unwrap();
+                let mut settings = settings_ref.borrow_mut();
+                if is_daemon_control(&name) {
+                    record_change(&app, &mut history_ref.borrow_mut(), &settings);
+                    settings.slider_moved = Some(std::time::Instant::now());
+                }
+                let previewing = settings.previewing();
                 match &*name {
                     "temperature" => {
                         settings.set_temperature(value as f64);
+                        app.global::<Parameters>().set_temperature(value);
+                        if previewing {
+                            preview_value(&app, &settings, &name, value as f64);
+                        } else {
+                            follow_temperature(&app, &mut settings);
+                        }
                     }
                     "brightness" => {
                         settings.set_brightness(value as f64);
+                        app.global::<Parameters>().set_brightness(value);
+                        if previewing {
+                            preview_value(&app, &settings, &name, value as f64);
+                        }
                     }
                     "gamma" => {
                         settings.set_gamma(value as f64);
+                        app.global::<Parameters>().set_gamma(value);
+                        if previewing {
+                            preview_value(&app, &settings, &name, value as f64);
+                        }
+                    }
+                    "backlight" => {
+                        settings.set_backlight(value as f64);
+                        app.global::<Parameters>().set_backlight(value);
+                    }
+                    "strength" if previewing => {
+                        app.global::<Parameters>().set_strength(value);
+                        preview_value(&app, &settings, &name, value as f64);
+                    }
+                    "strength" => apply_strength(&app, &mut settings, value as f64),
+                    name => {
+                        let mut displays = ddc_displays_ref.lock().expect("rust: unlock ddc");
+                        if let Some(index) = ddc_index(name) {
+                            if let Some(display) = displays.get_mut(index) {
+                                display.set_value(value as f64);
+                                app.global::<Parameters>()
+                                    .get_ddc_values()
+                                    .set_row_data(index, value);
+                                app.global::<Parameters>().set_value_text(
+                                    ddc_to_string(display.value, &display.label).into(),
+                                );
+                            }
+                        }
+                    }
+                }
+            });
+    }
+
+    // on slider grabbed or let go. with --commit on-release, what was held back
+    // while it was grabbed is written once it is let go.
+    {
+        let app_weak = app.as_weak();
+        let ticker_ref = shared.ticker.clone();
+        let settings_ref = shared.settings.clone();
+        let inhibit_ref = shared.idle_inhibit.clone();
+        app.global::<Parameters>()
+            .on_slider_held(move |name, held| {
+                ticker_ref.wake();
+                if let Some(inhibit) = &inhibit_ref {
+                    let _ = inhibit.send(held);
+                }
+                let app = app_weak.unwrap();
+                let mut settings = settings_ref.borrow_mut();
+                let previewing = settings.previewing();
+                settings.held = held;
+                if !previewing || held {
+                    return;
+                }
+                match &*name {
+                    "temperature" => follow_temperature(&app, &mut settings),
+                    "strength" => {
+                        let value = app.global::<Parameters>().get_strength();
+                        apply_strength(&app, &mut settings, value as f64);
                     }
                     _ => {}
                 }
@@ -426,86 +2138,740 @@ fn main() -> Result<(), AppletError> {
     }
 
     // create a timer that invokes tick on the main window
-    // and applies deltas in settings to the dbus server.
-    let timer = slint::Timer::default();
+    // and exchanges deltas and updates with the worker.
+    // while the window is settled and nothing is pending, tick slowly.
     {
         let app_weak = app.as_weak();
-        let proxy_ref = proxy.clone();
-        let settings_ref = settings.clone();
-        timer.start(
+        let settings_ref = shared.settings.clone();
+        let ticker_ref = shared.ticker.clone();
+        let rebuild = shared.daemonize.then(|| shared.clone());
+        let shared_ref = shared.clone();
+        let mut last_tick = std::time::Instant::now();
+        shared.ticker.timer.start(
             slint::TimerMode::Repeated,
-            std::time::Duration::from_millis(TICK_DELTA),
+            shared.ticker.interval,
             move || {
                 let app = app_weak.unwrap();
-                app.invoke_tick(TICK_DELTA as f32);
-
-                let proxy = proxy_ref.lock().expect("rust: unlock proxy");
-                let mut settings = settings_ref.lock().expect("rust: unlock settings");
-
-                if settings.invert.delta_accumulation != 0.0 {
-                    proxy.toggle_inverted().expect("rust: expect set inverted");
-                    settings.invalidate_deltas();
-                }
-
-                if settings.temperature.delta_accumulation != 0.0 {
-                    let server_value =
-                        proxy.temperature().expect("rust: get server temperature") as i16;
-                    let dbus_delta =
-                        ui_temperature_delta_to_dbus_value(settings.temperature.delta_accumulation);
-                    let (final_value, clamped_delta) = {
-                        let rounded_delta = dbus_temperature_rounded(dbus_delta);
-                        let proposed_final_value = server_value + rounded_delta;
-                        if proposed_final_value < 1000 {
-                            (1000, 1000 - server_value)
-                        } else if proposed_final_value > 10000 {
-                            (10000, 10000 - server_value)
-                        } else {
-                            (proposed_final_value, rounded_delta)
-                        }
-                    };
-                    if clamped_delta.abs() > 0 {
-                        app.global::<Parameters>()
-                            .set_value_text(dbus_temperature_to_string(final_value).into());
-                        proxy
-                            .update_temperature(clamped_delta)
-                            .expect("rust: expect set temperature");
-                        settings.invalidate_deltas();
+                // pass the measured time, so fades keep their speed at any tick rate.
+                // a fade starting from idle begins with a normal step instead of a jump.
+                let now = std::time::Instant::now();
+                let elapsed = if ticker_ref.idle.get() {
+                    ticker_ref.interval
+                } else {
+                    now.duration_since(last_tick)
+                };
+                let delta = elapsed.as_secs_f32() * 1000.0;
+                last_tick = now;
+                let opacity = app.global::<Parameters>().get_window_opacity();
+                let fade_in = app.global::<Startup>().get_fade_in();
+                let away = app.global::<Parameters>().get_away_ms();
+                app.invoke_tick(delta);
+                publish_window_state(&app, &shared_ref.window_state);
+                // once hidden, stop ticking and reading the daemon entirely.
+                // in background mode, prepare a fresh window for the next show.
+                if !app.window().is_visible() {
+                    ticker_ref.timer.stop();
+                    settings_ref.borrow().worker.park(true);
+                    if let Some(shared) = &rebuild {
+                        let shared = shared.clone();
+                        slint::Timer::single_shot(std::time::Duration::ZERO, move || {
+                            if let Err(e) = rebuild_window(&shared) {
+                                eprintln!("rust: rebuild window: {e}");
+                            }
+                        });
                     }
+                    return;
                 }
+                let fading = opacity != app.global::<Parameters>().get_window_opacity()
+                    || fade_in != app.global::<Startup>().get_fade_in()
+                    || away != app.global::<Parameters>().get_away_ms();
 
-                if settings.brightness.delta_accumulation != 0.0 {
-                    let server_value = proxy.brightness().expect("rust: get server brightness");
-                    let rounded_delta = dbus_brightness_rounded(ui_brightness_delta_to_dbus_value(
-                        settings.brightness.delta_accumulation,
-                    ));
-                    let final_value = server_value + rounded_delta;
-                    if final_value > 0.2 && final_value < 1.0 {
-                        app.global::<Parameters>()
-                            .set_value_text(dbus_brightness_to_string(final_value).into());
-                        proxy
-                            .update_brightness(rounded_delta)
-                            .expect("rust: expect set brightness");
-                        settings.invalidate_deltas();
-                    }
+                let mut settings = settings_ref.borrow_mut();
+                let syncing = sync_worker(&app, &mut settings);
+                guard_changes(&app, &shared_ref, &settings);
+                if fading || syncing || settings.backlight.delta_accumulation != 0.0 {
+                    ticker_ref.wake();
+                } else {
+                    ticker_ref.settle();
                 }
 
-                if settings.gamma.delta_accumulation != 0.0 {
-                    let server_value = proxy.gamma().expect("rust: get server gamma");
-                    let rounded_delta =
-                        dbus_gamma_rounded(settings.gamma.delta_accumulation as f64);
-                    let final_value = server_value + rounded_delta;
-                    if final_value < 1.5 && final_value > 0.5 {
-                        app.global::<Parameters>()
-                            .set_value_text(dbus_gamma_to_string(final_value).into());
-                        proxy
-                            .update_gamma(rounded_delta)
-                            .expect("rust: expect set gamma");
-                        settings.invalidate_deltas();
-                    }
-                }
+                sync_backlight(&app, &mut settings);
             },
         );
     }
+}
+
+fn main() -> std::process::ExitCode {
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("wl-gammarelay-applet: {}", e.message());
+            std::process::ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-    Ok(app.run()?)
+fn run() -> Result<(), AppletError> {
+    let args = parse_args();
+    sandbox::init(args.sandbox);
+    seat::init(args.seat.clone());
+    daemon::init(std::time::Duration::from_secs(args.startup_timeout as u64));
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Status => commands::status(&create_proxy(None)?),
+            Command::Outputs => commands::outputs(&create_proxy(None)?),
+            Command::Doctor => commands::doctor(args.config.as_deref()),
+            Command::InstallService { systemd, after } => {
+                commands::install_service(*systemd, after.as_deref())
+            }
+            Command::UninstallService => commands::uninstall_service(),
+            Command::Config {
+                command: ConfigCommand::Dump,
+            } => commands::config_dump(&Config::load(args.config.clone())?, &args),
+        };
+    }
+    if let Some(ticks) = args.bench_ticks {
+        return bench::run(ticks);
+    }
+    if args.per_output {
+        return spawn_per_output();
+    }
+    if let Some(format) = &args.watch {
+        return commands::watch(
+            &create_proxy(args.output.as_deref())?,
+            format,
+            args.format_mode,
+            args.output.as_deref(),
+        );
+    }
+    if let Some(path) = &args.replay {
+        return record::replay(&create_proxy(args.output.as_deref())?, path);
+    }
+    if let Some(temporary) = &args.temporary {
+        let connection = Connection::session()?;
+        let body = (temporary.preset.as_str(), temporary.minutes);
+        if ipc::call_running(&connection, args.output.as_deref(), "Temporary", &body)? {
+            return Ok(());
+        }
+    }
+    if args.daemonize {
+        let connection = Connection::session()?;
+        if ipc::call_running(&connection, args.output.as_deref(), "Toggle", &())? {
+            return Ok(());
+        }
+        // keep the event loop running while the window is hidden.
+        let backend = i_slint_backend_winit::Backend::new()?;
+        slint::platform::Platform::set_event_loop_quit_on_last_window_closed(&backend, false);
+        slint::platform::set_platform(Box::new(backend))
+            .map_err(|e| slint::PlatformError::Other(format!("{e:?}")))?;
+    }
+    let mut config = Config::load(args.config.clone())?;
+    if let Some(output) = &args.output {
+        config.select_output(output);
+    }
+    if args.cycle_preset {
+        return commands::cycle_preset(
+            &create_proxy(args.output.as_deref())?,
+            &config,
+            args.output.as_deref(),
+        );
+    }
+    if args.toggle_gamma {
+        return commands::toggle_gamma(
+            &create_proxy(args.output.as_deref())?,
+            &config,
+            args.output.as_deref(),
+        );
+    }
+
+    let app = WlGammaRelayApplet::new()?;
+    let proxy = create_proxy(args.output.as_deref())?;
+    let capabilities = Capabilities::detect(&proxy)?;
+    if let Some(output) = &args.output {
+        if !capabilities
+            .outputs
+            .as_ref()
+            .is_some_and(|outputs| outputs.contains(output))
+        {
+            return Err(AppletError::UnknownOutput(output.clone()));
+        }
+        app.global::<Startup>().set_output(output.into());
+    }
+    app.global::<Startup>().set_about_text(
+        format!(
+            "wl-gammarelay-applet {}\ndaemon: {DAEMON_SERVICE} {}\nbus: {}\noutput: {}",
+            env!("CARGO_PKG_VERSION"),
+            capabilities.version_string(),
+            seat::bus_description(),
+            args.output.as_deref().unwrap_or("all"),
+        )
+        .into(),
+    );
+    let backlight = if args.backlight {
+        Backlight::detect()?
+    } else {
+        None
+    };
+    let ddc_displays = Arc::<Mutex<Vec<DdcDisplay>>>::default();
+    let night_saved = Rc::<RefCell<Option<Preset>>>::default();
+
+    // initialize window state and ui values
+    let settings = {
+        // initialize startup ui parameters based on arguments
+        let default_temperature = args.default_temperature as f64;
+        let default_brightness = args.default_brightness;
+        let default_gamma = args.default_gamma;
+
+        // hide controls the daemon is too old to support, warning if they were requested.
+        let supported = |name: &str, hidden: bool| {
+            let supported = capabilities.supports_control(name);
+            if !supported && !hidden {
+                eprintln!(
+                    "rust: daemon (version {}) does not support {name}; hiding it",
+                    capabilities.version_string()
+                );
+            }
+            supported && !hidden
+        };
+        app.global::<Startup>()
+            .set_show_invert(supported("invert", args.hide_invert));
+        app.global::<Startup>()
+            .set_has_grayscale(capabilities.supports_control("grayscale"));
+        app.global::<Startup>()
+            .set_has_invert(capabilities.supports_control("invert"));
+        app.global::<Startup>().set_show_temperature(supported(
+            "temperature",
+            args.hide_temperature || args.strength,
+        ));
+        app.global::<Startup>().set_show_brightness(supported(
+            "brightness",
+            args.hide_brightness || args.strength,
+        ));
+        app.global::<Startup>().set_show_strength(
+            args.strength && supported("temperature", false) && supported("brightness", false),
+        );
+        app.global::<Startup>()
+            .set_show_gamma(supported("gamma", args.hide_gamma));
+        app.global::<Startup>()
+            .set_backlight_available(backlight.is_some());
+        app.global::<Startup>()
+            .set_show_backlight(backlight.is_some());
+        app.global::<Startup>()
+            .set_order(slint::ModelRc::new(slint::VecModel::from(
+                config
+                    .order
+                    .iter()
+                    .map(slint::SharedString::from)
+                    .collect::<Vec<_>>(),
+            )));
+        apply_control_order(&app);
+        app.global::<Startup>()
+            .set_show_night_mode(config.night_preset().is_some());
+        app.global::<Startup>()
+            .set_show_timer(config.preset(&config.timer.preset).is_some());
+        app.global::<Startup>()
+            .set_timer_preset(config.timer.preset.as_str().into());
+        app.global::<Startup>()
+            .set_timer_minutes(config.timer.minutes as i32);
+        app.global::<Startup>().set_show_gamma_toggle(
+            config.gamma_toggle.button && capabilities.supports_control("gamma"),
+        );
+        app.global::<Startup>()
+            .set_show_link(!config.link.points.is_empty());
+        app.global::<Parameters>().set_linked(config.link.enabled);
+        app.global::<Startup>().set_show_caret(!args.hide_caret);
+        app.global::<Startup>().set_show_labels(!args.hide_labels);
+        app.global::<Startup>()
+            .set_show_step_buttons(args.step_buttons && !args.read_only);
+        app.global::<Startup>().set_show_value(!args.hide_value);
+        app.global::<Startup>().set_never_fade(args.never_fade);
+        app.global::<Startup>().set_read_only(args.read_only);
+        app.global::<Startup>()
+            .set_fade_grace_ms(args.fade_grace_ms as f32);
+        app.global::<Startup>().set_animations(!args.no_animations);
+        if let Some(font) = &args.font {
+            app.global::<Startup>().set_font_family(font.into());
+        }
+        app.global::<Startup>().set_font_size(args.font_size as f32);
+        if let Some(color) = hex_color(&args.highlight_color) {
+            app.global::<Startup>().set_highlight_color(color);
+        }
+        app.global::<Startup>()
+            .set_outer_padding(args.outer_padding as i32);
+        app.global::<Startup>()
+            .set_window_height(args.window_height as i32);
+        app.global::<Startup>()
+            .set_window_width(args.window_width as i32);
+        app.global::<Startup>()
+            .set_default_temperature(
+                dbus_temperature_to_ui_value(default_temperature as u16) as f32
+            );
+        app.global::<Startup>()
+            .set_default_brightness(dbus_brightness_to_ui_value(default_brightness) as f32);
+        app.global::<Startup>()
+            .set_default_gamma(dbus_gamma_to_ui_value(default_gamma) as f32);
+
+        let startup_backlight = match &backlight {
+            Some(backlight) => backlight.value()?,
+            None => 0.0,
+        };
+        let (worker, daemon) = Worker::spawn(
+            proxy,
+            backlight,
+            worker::Options {
+                has_invert: capabilities.supports_control("invert"),
+                has_gamma: capabilities.supports_control("gamma"),
+                has_grayscale: capabilities.supports_control("grayscale"),
+                temperature_rounding: config.rounding.temperature,
+                poll_interval: args.poll_interval.map(std::time::Duration::from_millis),
+            },
+        )?;
+        let startup_temperature = dbus_temperature_to_ui_value(daemon.temperature);
+        let startup_brightness = dbus_brightness_to_ui_value(daemon.brightness);
+        let startup_gamma = dbus_gamma_to_ui_value(daemon.gamma);
+
+        // show the value of the first visible slider.
+        let first_slider = config
+            .order
+            .iter()
+            .find(|name| name.as_str() != "invert" && control_visible(&app, name));
+        match first_slider.map(String::as_str) {
+            Some("temperature") => app.global::<Parameters>().set_value_text(
+                dbus_temperature_to_string(
+                    daemon.temperature as i16,
+                    config.rounding.temperature_display,
+                )
+                .into(),
+            ),
+            Some("brightness") => app.global::<Parameters>().set_value_text(
+                dbus_brightness_to_string(daemon.brightness, args.raw_brightness).into(),
+            ),
+            Some("gamma") => app
+                .global::<Parameters>()
+                .set_value_text(dbus_gamma_to_string(daemon.gamma).into()),
+            Some("backlight") => app
+                .global::<Parameters>()
+                .set_value_text(backlight_to_string(startup_backlight).into()),
+            Some("strength") => app.global::<Parameters>().set_value_text(
+                strength_to_string(config.strength.at_temperature(daemon.temperature)).into(),
+            ),
+            _ => app.global::<Startup>().set_show_value(false),
+        }
+
+        // initialize parameter ui values based on current gammarelay state
+        app.global::<Parameters>().set_invert(daemon.inverted);
+        app.global::<Parameters>().set_grayscale(daemon.grayscale);
+        app.global::<Parameters>()
+            .set_temperature(startup_temperature as f32);
+        app.global::<Parameters>()
+            .set_brightness(startup_brightness as f32);
+        app.global::<Parameters>().set_gamma(startup_gamma as f32);
+        app.global::<Parameters>()
+            .set_strength(config.strength.at_temperature(daemon.temperature) as f32);
+        if let Some(night_preset) = config.night_preset() {
+            app.global::<Parameters>()
+                .set_night_mode(capture_preset(&daemon, night_preset) == night_preset);
+        }
+        app.global::<Parameters>()
+            .set_backlight(startup_backlight as f32);
+        app.global::<Startup>()
+            .set_default_backlight(startup_backlight as f32);
+
+        Rc::new(RefCell::new(Settings {
+            temperature: SettingState {
+                value: startup_temperature,
+                delta_accumulation: 0.0,
+                default: default_temperature,
+            },
+            brightness: SettingState {
+                value: startup_brightness,
+                delta_accumulation: 0.0,
+                default: default_brightness,
+            },
+            gamma: SettingState {
+                value: startup_gamma,
+                delta_accumulation: 0.0,
+                default: default_gamma,
+            },
+            backlight: SettingState {
+                value: startup_backlight,
+                delta_accumulation: 0.0,
+                default: startup_backlight,
+            },
+            rounding: config.rounding,
+            settle: std::time::Duration::from_millis(config.smoothing.settle_ms),
+            slider_moved: None,
+            commit: args.commit,
+            reconcile: args.on_external,
+            held: false,
+            raw_brightness: args.raw_brightness,
+            link: config.link.clone(),
+            strength: config.strength,
+            daemon,
+            worker,
+            deltas_in_flight: None,
+            deltas_sent: Deltas::default(),
+            deltas_unapplied: Deltas::default(),
+            resync_pending: None,
+        }))
+    };
+
+    // nothing has been written yet, so the worker's state is the startup state.
+    let restore = args.restore_on_exit.map(|mode| {
+        let state = match mode {
+            RestoreMode::Startup => settings.borrow().daemon,
+            RestoreMode::Defaults => DaemonState {
+                inverted: false,
+                grayscale: false,
+                temperature: args.default_temperature as u16,
+                brightness: args.default_brightness,
+                gamma: args.default_gamma,
+            },
+        };
+        Restore::new(
+            args.output.as_deref(),
+            state,
+            capabilities.supports_control("invert"),
+            capabilities.supports_control("gamma"),
+            capabilities.supports_control("grayscale"),
+        )
+    });
+
+    let ticker = Rc::new(Ticker {
+        timer: slint::Timer::default(),
+        interval: std::time::Duration::from_millis(args.tick_ms),
+        idle: Cell::new(false),
+    });
+
+    // keep the screen from locking or dimming in the middle of a drag. compositors
+    // without wlr-layer-shell or idle-inhibit, such as gnome, go without.
+    let idle_inhibit = (!args.read_only).then(|| {
+        let (idle_inhibit, requests) = std::sync::mpsc::channel();
+        spawn(move || match inhibit::run(requests) {
+            Ok(())
+            | Err(AppletError::WaylandBind(wayland_client::globals::BindError::NotPresent)) => {}
+            Err(e) => eprintln!("rust: inhibit idle while dragging: {e}"),
+        });
+        idle_inhibit
+    });
+
+    // window states for the dbus interface, published once it is served.
+    let (window_states, window_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    let shared = Shared {
+        settings,
+        config: Rc::new(RefCell::new(config)),
+        ticker,
+        night_saved,
+        override_saved: Rc::default(),
+        history: Rc::default(),
+        schedule_applied: Rc::default(),
+        timer: Rc::default(),
+        guarded: Rc::default(),
+        guard_timer: Rc::default(),
+        ddc_displays,
+        idle_inhibit,
+        window_state: Rc::new(ipc::WindowPublisher::new(window_states)),
+        window: Rc::default(),
+        current: Arc::new(Mutex::new(app.as_weak())),
+        safety_revert: args.safety_revert,
+        focused_output: args.focused_output,
+        daemonize: args.daemonize,
+    };
+    connect_window(&app, &shared);
+    if let Some(temporary) = &args.temporary {
+        app.global::<Parameters>()
+            .invoke_temporary_preset(temporary.preset.as_str().into(), temporary.minutes as i32);
+    }
+
+    // follow the desktop's light and dark mode with the configured presets.
+    for source in shared.config.borrow().theme.sources() {
+        let current = shared.current.clone();
+        let theme = shared.config.borrow().theme.clone();
+        spawn(move || {
+            let result = Connection::session().and_then(|connection| {
+                source.watch(&connection, |mode| {
+                    let name = slint::SharedString::from(theme.preset_name(mode));
+                    let current = current.lock().expect("rust: unlock window");
+                    let _ = current.upgrade_in_event_loop(move |app| {
+                        app.global::<Parameters>().invoke_apply_preset(name)
+                    });
+                })
+            });
+            if let Err(e) = result {
+                eprintln!("rust: watch {source:?} theme: {e}");
+            }
+        });
+    }
+
+    // honor the desktop's reduced-motion setting. the portal may take a moment
+    // to start, so it is asked in the background.
+    if !args.no_animations {
+        let current = shared.current.clone();
+        spawn(move || {
+            let reduced = Connection::session()
+                .and_then(|connection| theme::reduced_motion(&connection))
+                .unwrap_or(false);
+            if reduced {
+                let current = current.lock().expect("rust: unlock window");
+                let _ = current
+                    .upgrade_in_event_loop(|app| app.global::<Startup>().set_animations(false));
+            }
+        });
+    }
+
+    // apply presets for fullscreen windows and the applications in the rules.
+    if shared.config.borrow().game_mode.enabled || !shared.config.borrow().rules.is_empty() {
+        let current = shared.current.clone();
+        let config = shared.config.borrow().clone();
+        spawn(move || {
+            let mut applied = slint::SharedString::default();
+            let result = toplevel::watch(move |toplevels| {
+                let name =
+                    slint::SharedString::from(config.override_preset(toplevels).unwrap_or(""));
+                if name == applied {
+                    return;
+                }
+                applied = name.clone();
+                let current = current.lock().expect("rust: unlock window");
+                let _ = current.upgrade_in_event_loop(move |app| {
+                    app.global::<Parameters>().invoke_override_preset(name)
+                });
+            });
+            if let Err(e) = result {
+                eprintln!("rust: watch windows for game mode and rules: {e}");
+            }
+        });
+    }
+
+    // give outputs still at the daemon's defaults the values of their profiles.
+    for output in capabilities.outputs.iter().flatten() {
+        if args.output.as_ref().is_some_and(|own| own != output) {
+            continue;
+        }
+        if let Some(profile) = shared.config.borrow().output_profile(output) {
+            let output = output.clone();
+            let values = profile.values();
+            spawn(move || {
+                if let Err(e) = commands::apply_output_profile(&output, values, true) {
+                    eprintln!("rust: apply profile of output {output}: {e}");
+                }
+            });
+        }
+    }
+
+    // bring monitors connected while the applet runs to its values.
+    if capabilities.outputs.is_some() {
+        let current = shared.current.clone();
+        spawn(move || {
+            let result = wayland::watch_outputs(|name, connected| {
+                if !connected {
+                    return;
+                }
+                let name = slint::SharedString::from(name);
+                let current = current.lock().expect("rust: unlock window");
+                let _ = current.upgrade_in_event_loop(move |app| {
+                    app.global::<Parameters>().invoke_output_connected(name)
+                });
+            });
+            if let Err(e) = result {
+                eprintln!("rust: watch outputs: {e}");
+            }
+        });
+    }
+
+    // recompute the schedule every minute, and once right after startup.
+    let schedule_timer = slint::Timer::default();
+    if shared.config.borrow().schedule.enabled {
+        let current = shared.current.clone();
+        let tick = move || {
+            if let Some(app) = current.lock().expect("rust: unlock window").upgrade() {
+                app.global::<Parameters>().invoke_schedule_tick();
+            }
+        };
+        slint::Timer::single_shot(std::time::Duration::ZERO, tick.clone());
+        schedule_timer.start(
+            slint::TimerMode::Repeated,
+            std::time::Duration::from_secs(60),
+            tick,
+        );
+    }
+
+    // log the daemon values as they change, for --replay.
+    if let Some(path) = args.record.clone() {
+        let output = args.output.clone();
+        spawn(move || {
+            let result =
+                create_proxy(output.as_deref()).and_then(|proxy| record::record(&proxy, &path));
+            if let Err(e) = result {
+                eprintln!("rust: record to {}: {e}", path.display());
+            }
+        });
+    }
+
+    // show changes to the daemon while the window is hidden in an overlay.
+    if args.osd {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        spawn(move || {
+            if let Err(e) = osd::run(receiver) {
+                eprintln!("rust: show overlay: {e}");
+            }
+        });
+        let current = shared.current.clone();
+        let output = args.output.clone();
+        spawn(move || {
+            let result = create_proxy(output.as_deref()).and_then(|proxy| {
+                commands::follow(&proxy, |before, state| {
+                    let Some(osd) = before.and_then(|before| osd::Osd::describe(before, state))
+                    else {
+                        return;
+                    };
+                    let sender = sender.clone();
+                    let current = current.lock().expect("rust: unlock window");
+                    let _ = current.upgrade_in_event_loop(move |app| {
+                        if !app.window().is_visible() {
+                            let _ = sender.send(osd);
+                        }
+                    });
+                })
+            });
+            if let Err(e) = result {
+                eprintln!("rust: follow daemon for overlay: {e}");
+            }
+        });
+    }
+
+    // keep the values in a strip on a screen edge, which toggles the window.
+    if let Some(edge) = args.dock {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (mut wake, woken) = std::os::unix::net::UnixStream::pair()?;
+        let current = shared.current.clone();
+        spawn(move || {
+            let clicked = || {
+                let current = current.lock().expect("rust: unlock window");
+                let _ =
+                    current.upgrade_in_event_loop(|app| app.global::<Parameters>().invoke_toggle());
+            };
+            if let Err(e) = dock::run(edge, receiver, woken, clicked) {
+                eprintln!("rust: show dock: {e}");
+            }
+        });
+        let output = args.output.clone();
+        spawn(move || {
+            let result = create_proxy(output.as_deref()).and_then(|proxy| {
+                commands::follow(&proxy, |_, state| {
+                    if sender.send(*state).is_ok() {
+                        let _ = std::io::Write::write_all(&mut wake, &[1]);
+                    }
+                })
+            });
+            if let Err(e) = result {
+                eprintln!("rust: follow daemon for dock: {e}");
+            }
+        });
+    }
+
+    // sample the screen content for auto-brightness.
+    if shared.config.borrow().auto_brightness.enabled {
+        let current = shared.current.clone();
+        let interval =
+            std::time::Duration::from_millis(shared.config.borrow().auto_brightness.interval_ms);
+        spawn(move || {
+            let result = screencopy::watch(interval, |luminance| {
+                let current = current.lock().expect("rust: unlock window");
+                let _ = current.upgrade_in_event_loop(move |app| {
+                    app.global::<Parameters>()
+                        .invoke_content_sampled(luminance as f32)
+                });
+            });
+            if let Err(e) = result {
+                eprintln!("rust: sample screen content for auto-brightness: {e}");
+            }
+        });
+    }
+
+    // probe ddc displays in the background, as ddcutil can take seconds,
+    // then add their sliders to the layout.
+    if args.ddc {
+        let current = shared.current.clone();
+        let ddc_displays_ref = shared.ddc_displays.clone();
+        spawn(move || {
+            let displays = match DdcDisplay::detect() {
+                Ok(displays) => displays,
+                Err(e) => {
+                    eprintln!("rust: detect ddc displays: {e}");
+                    return;
+                }
+            };
+            let values: Vec<f32> = displays.iter().map(|d| d.value as f32).collect();
+            let labels: Vec<slint::SharedString> =
+                displays.iter().map(|d| d.label.as_str().into()).collect();
+            *ddc_displays_ref.lock().expect("rust: unlock ddc") = displays;
+            let current = current.lock().expect("rust: unlock window");
+            let _ = current.upgrade_in_event_loop(move |app| {
+                app.global::<Parameters>()
+                    .set_ddc_values(slint::ModelRc::new(slint::VecModel::from(values.clone())));
+                app.global::<Startup>()
+                    .set_ddc_defaults(slint::ModelRc::new(slint::VecModel::from(values)));
+                app.global::<Startup>()
+                    .set_ddc_labels(slint::ModelRc::new(slint::VecModel::from(labels)));
+                app.global::<Startup>().set_show_ddc(true);
+                apply_control_order(&app);
+            });
+        });
+    }
+
+    // once the window is shown, ask the compositor to move it where the focus is.
+    if args.focused_output && !args.daemonize {
+        slint::Timer::single_shot(std::time::Duration::ZERO, || {
+            spawn(|| {
+                if let Err(e) = compositor::move_to_focused_output() {
+                    eprintln!("rust: move window to focused output: {e}");
+                }
+            });
+        });
+    }
+
+    // serve the applet's own dbus interface for scripts. the runtime drives the
+    // connection in the background and must outlive the event loop.
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _ipc_connection =
+        match runtime.block_on(ipc::serve(shared.current.clone(), args.output.as_deref())) {
+            Ok(connection) => {
+                let published = connection.clone();
+                runtime.spawn(async move {
+                    if let Err(e) = ipc::publish_window(published, window_receiver).await {
+                        eprintln!("rust: publish window state: {e}");
+                    }
+                });
+                Some(connection)
+            }
+            Err(e) => {
+                eprintln!("rust: applet dbus interface unavailable: {e}");
+                None
+            }
+        };
+
+    if let Some(restore) = &restore {
+        restore.install(&runtime)?;
+    }
+    // a systemd unit counts as started once the applet can be called.
+    notify::ready();
+
+    // in background mode the window starts hidden, and nothing ticks until it is shown.
+    if args.daemonize {
+        shared.ticker.timer.stop();
+        shared.settings.borrow().worker.park(true);
+    } else {
+        app.show()?;
+    }
+    *shared.window.borrow_mut() = Some(app);
+    let result = slint::run_event_loop();
+    if args.print_on_exit {
+        commands::print_values(&shared.settings.borrow().daemon, args.output.as_deref());
+    }
+    if let Some(restore) = &restore {
+        restore.run();
+    }
+    Ok(result?)
 }
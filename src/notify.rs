@@ -0,0 +1,31 @@
+use std::{
+    ffi::OsStr,
+    os::{
+        linux::net::SocketAddrExt,
+        unix::{
+            ffi::OsStrExt,
+            net::{SocketAddr, UnixDatagram},
+        },
+    },
+};
+
+// tell systemd the applet is up, for a unit of Type=notify. does nothing unless
+// systemd passed a socket to report to.
+pub fn ready() {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = send(&socket, "READY=1") {
+        eprintln!("rust: notify systemd: {e}");
+    }
+}
+
+// the socket is a path, or an abstract name when it starts with '@'.
+fn send(socket: &OsStr, state: &str) -> std::io::Result<()> {
+    let address = match socket.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
@@ -0,0 +1,330 @@
+use std::{
+    ops::RangeInclusive,
+    os::{fd::AsFd, unix::fs::FileExt},
+    sync::mpsc::Receiver,
+    time::Duration,
+};
+
+use wayland_client::{
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_buffer, wl_compositor, wl_region, wl_registry, wl_shm, wl_shm_pool, wl_surface},
+    Connection, Dispatch, EventQueue, QueueHandle,
+};
+use wayland_protocols_wlr::layer_shell::v1::client::{
+    zwlr_layer_shell_v1::{Layer, ZwlrLayerShellV1},
+    zwlr_layer_surface_v1::{self, Anchor, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+};
+
+use crate::{
+    config::{BRIGHTNESS_RANGE, GAMMA_RANGE, TEMPERATURE_RANGE},
+    wayland,
+    worker::DaemonState,
+    AppletError,
+};
+
+// how long the overlay stays after the last change.
+const SHOWN: Duration = Duration::from_secs(1);
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 48;
+// distance from the bottom edge of the screen.
+const MARGIN: i32 = 96;
+// glyphs are drawn at twice their size, one column apart.
+const SCALE: u32 = 2;
+const ADVANCE: u32 = 6 * SCALE;
+const TEXT_TOP: u32 = 8;
+const BAR_LEFT: u32 = 16;
+const BAR_TOP: u32 = 32;
+const BAR_WIDTH: u32 = WIDTH - 2 * BAR_LEFT;
+const BAR_HEIGHT: u32 = 6;
+// premultiplied argb.
+const BACKGROUND: u32 = 0xcc0d0d0d;
+const TRACK: u32 = 0xff404040;
+const FOREGROUND: u32 = 0xffffffff;
+
+// what the overlay shows: a value and where it is within its range.
+pub struct Osd {
+    pub text: String,
+    pub fraction: f64,
+}
+
+impl Osd {
+    // the first value that changed between two daemon states, if any.
+    pub fn describe(before: &DaemonState, after: &DaemonState) -> Option<Self> {
+        let fraction = |value: f64, range: &RangeInclusive<f64>| {
+            ((value - range.start()) / (range.end() - range.start())).clamp(0.0, 1.0)
+        };
+        let temperature_range = *TEMPERATURE_RANGE.start() as f64..=*TEMPERATURE_RANGE.end() as f64;
+        if after.temperature != before.temperature {
+            Some(Self {
+                text: format!("{} K", after.temperature),
+                fraction: fraction(after.temperature as f64, &temperature_range),
+            })
+        } else if (after.brightness - before.brightness).abs() > 0.001 {
+            Some(Self {
+                text: format!("{:.0} %", after.brightness * 100.0),
+                fraction: fraction(after.brightness, &BRIGHTNESS_RANGE),
+            })
+        } else if (after.gamma - before.gamma).abs() > 0.001 {
+            Some(Self {
+                text: format!("{:.2} γ", after.gamma),
+                fraction: fraction(after.gamma, &GAMMA_RANGE),
+            })
+        } else if after.inverted != before.inverted {
+            Some(Self {
+                text: if after.inverted {
+                    "INVERT ON"
+                } else {
+                    "INVERT OFF"
+                }
+                .to_string(),
+                fraction: if after.inverted { 1.0 } else { 0.0 },
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// a 5x7 bitmap of the few characters the overlay shows, one row per byte.
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; 7] {
+    match c {
+        '0' => [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+        '1' => [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        '2' => [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+        '3' => [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+        '4' => [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+        '5' => [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+        '6' => [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+        '7' => [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+        '8' => [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+        '9' => [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+        '.' => [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b01100, 0b01100],
+        '%' => [0b11000, 0b11001, 0b00010, 0b00100, 0b01000, 0b10011, 0b00011],
+        'γ' => [0b00000, 0b00000, 0b10001, 0b01010, 0b00100, 0b01010, 0b00100],
+        'E' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111],
+        'F' => [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000],
+        'I' => [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+        'K' => [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001],
+        'N' => [0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001, 0b10001],
+        'O' => [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110],
+        'R' => [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001],
+        'T' => [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100],
+        'V' => [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100],
+        _ => [0; 7],
+    }
+}
+
+// the overlay's pixels as little-endian argb: the text centered above the bar.
+fn render(osd: &Osd) -> Vec<u8> {
+    let mut pixels = vec![BACKGROUND; (WIDTH * HEIGHT) as usize];
+    let mut fill = |left: u32, top: u32, width: u32, height: u32, color: u32| {
+        for y in top..top + height {
+            let row = (y * WIDTH) as usize;
+            pixels[row + left as usize..row + (left + width) as usize].fill(color);
+        }
+    };
+    let glyphs: Vec<[u8; 7]> = osd
+        .text
+        .chars()
+        .take((WIDTH / ADVANCE) as usize)
+        .map(glyph)
+        .collect();
+    let text_width = (glyphs.len() as u32 * ADVANCE).saturating_sub(SCALE);
+    let mut left = (WIDTH - text_width) / 2;
+    for rows in glyphs {
+        for (y, bits) in (0..).zip(rows) {
+            for x in 0..5 {
+                if bits >> (4 - x) & 1 == 1 {
+                    fill(
+                        left + x * SCALE,
+                        TEXT_TOP + y * SCALE,
+                        SCALE,
+                        SCALE,
+                        FOREGROUND,
+                    );
+                }
+            }
+        }
+        left += ADVANCE;
+    }
+    fill(BAR_LEFT, BAR_TOP, BAR_WIDTH, BAR_HEIGHT, TRACK);
+    let filled = (BAR_WIDTH as f64 * osd.fraction.clamp(0.0, 1.0)).round() as u32;
+    fill(BAR_LEFT, BAR_TOP, filled, BAR_HEIGHT, FOREGROUND);
+    pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_le_bytes())
+        .collect()
+}
+
+#[derive(Default)]
+struct State {
+    configured: bool,
+    closed: bool,
+    // buffers the compositor has not released yet.
+    buffers: Vec<wl_buffer::WlBuffer>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrLayerSurfaceV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        layer_surface: &ZwlrLayerSurfaceV1,
+        event: zwlr_layer_surface_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
+                layer_surface.ack_configure(serial);
+                state.configured = true;
+            }
+            zwlr_layer_surface_v1::Event::Closed => state.closed = true,
+            _ => {}
+        }
+    }
+}
+
+// each drawing gets its own buffer, dropped once the compositor is done with it.
+impl Dispatch<wl_buffer::WlBuffer, ()> for State {
+    fn event(
+        state: &mut Self,
+        buffer: &wl_buffer::WlBuffer,
+        event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+            state.buffers.retain(|b| b != buffer);
+        }
+    }
+}
+
+delegate_noop!(State: wl_compositor::WlCompositor);
+delegate_noop!(State: wl_region::WlRegion);
+delegate_noop!(State: wl_shm_pool::WlShmPool);
+delegate_noop!(State: ZwlrLayerShellV1);
+delegate_noop!(State: ignore wl_shm::WlShm);
+delegate_noop!(State: ignore wl_surface::WlSurface);
+
+fn draw(
+    queue: &mut EventQueue<State>,
+    state: &mut State,
+    shm: &wl_shm::WlShm,
+    surface: &wl_surface::WlSurface,
+    osd: &Osd,
+) -> Result<(), AppletError> {
+    let data = render(osd);
+    let file = wayland::shm_file("osd", data.len() as u64)?;
+    file.write_all_at(&data, 0)?;
+    let qhandle = queue.handle();
+    let pool = shm.create_pool(file.as_fd(), data.len() as i32, &qhandle, ());
+    let buffer = pool.create_buffer(
+        0,
+        WIDTH as i32,
+        HEIGHT as i32,
+        (WIDTH * 4) as i32,
+        wl_shm::Format::Argb8888,
+        &qhandle,
+        (),
+    );
+    pool.destroy();
+    surface.attach(Some(&buffer), 0, 0);
+    surface.damage(0, 0, WIDTH as i32, HEIGHT as i32);
+    surface.commit();
+    state.buffers.push(buffer);
+    queue.roundtrip(state)?;
+    Ok(())
+}
+
+// show each value received in a small overlay at the bottom of the screen,
+// like a volume popup, until none follows for a second. clicks go through it.
+// runs until the sender is dropped, so it belongs on its own thread. needs a
+// compositor with wlr-layer-shell, such as sway or hyprland.
+pub fn run(updates: Receiver<Osd>) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+    let qhandle = queue.handle();
+    let compositor: wl_compositor::WlCompositor = globals.bind(&qhandle, 1..=4, ())?;
+    let shm: wl_shm::WlShm = globals.bind(&qhandle, 1..=1, ())?;
+    let layer_shell: ZwlrLayerShellV1 = globals.bind(&qhandle, 1..=4, ())?;
+    let mut state = State::default();
+    while let Ok(mut osd) = updates.recv() {
+        let surface = compositor.create_surface(&qhandle, ());
+        let region = compositor.create_region(&qhandle, ());
+        surface.set_input_region(Some(&region));
+        region.destroy();
+        let layer_surface = layer_shell.get_layer_surface(
+            &surface,
+            None,
+            Layer::Overlay,
+            "wl-gammarelay-applet-osd".to_string(),
+            &qhandle,
+            (),
+        );
+        layer_surface.set_size(WIDTH, HEIGHT);
+        layer_surface.set_anchor(Anchor::Bottom);
+        layer_surface.set_margin(0, 0, MARGIN, 0);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+        surface.commit();
+        state.configured = false;
+        state.closed = false;
+        while !state.configured && !state.closed {
+            queue.blocking_dispatch(&mut state)?;
+        }
+        while !state.closed {
+            draw(&mut queue, &mut state, &shm, &surface, &osd)?;
+            let Ok(next) = updates.recv_timeout(SHOWN) else {
+                break;
+            };
+            osd = next;
+        }
+        layer_surface.destroy();
+        surface.destroy();
+        for buffer in state.buffers.drain(..) {
+            buffer.destroy();
+        }
+        queue.roundtrip(&mut state)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: DaemonState = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: 5500,
+        brightness: 0.8,
+        gamma: 1.0,
+    };
+
+    #[test]
+    fn overlay_shows_the_value_that_changed() {
+        let brighter = DaemonState {
+            brightness: 0.5,
+            ..START
+        };
+        let osd = Osd::describe(&START, &brighter).expect("rust: brightness changed");
+        assert_eq!(osd.text, "50 %");
+        assert_eq!(osd.fraction, 0.5);
+        assert!(Osd::describe(&START, &START).is_none());
+    }
+}
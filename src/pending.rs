@@ -0,0 +1,90 @@
+use std::{
+    collections::VecDeque,
+    mem::discriminant,
+    time::{Duration, Instant},
+};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+// slider movement not yet applied to the daemon, in ui units.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Deltas {
+    pub temperature: f64,
+    pub brightness: f64,
+    pub gamma: f64,
+}
+
+// a write for the worker. absolute values can be retried safely, unlike the
+// daemon's relative updates and toggle; deltas clear each part once applied.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Invert(bool),
+    Grayscale(bool),
+    Temperature(u16),
+    Brightness(f64),
+    Gamma(f64),
+    Backlight(f64),
+    Deltas(Deltas),
+    // re-read all daemon values, after the writes queued before it.
+    Refresh,
+}
+
+// writes waiting for the worker. when a write fails it stays queued, and
+// the worker waits an exponentially growing delay before trying again.
+#[derive(Default)]
+pub struct PendingOps {
+    ops: VecDeque<Op>,
+    backoff: Option<Duration>,
+    retry_at: Option<Instant>,
+}
+
+impl PendingOps {
+    // a newer value for a control replaces any queued one.
+    pub fn push(&mut self, op: Op) {
+        self.ops
+            .retain(|queued| discriminant(queued) != discriminant(&op));
+        self.ops.push_back(op);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    // when to try again, if writes are waiting out a backoff.
+    pub fn retry_at(&self) -> Option<Instant> {
+        self.retry_at.filter(|_| !self.ops.is_empty())
+    }
+
+    pub fn ready(&self, now: Instant) -> bool {
+        self.retry_at.is_none_or(|retry_at| now >= retry_at)
+    }
+
+    // write queued ops in order, stopping at the first failure. a write may
+    // update its op to record partial progress.
+    pub fn flush(
+        &mut self,
+        mut write: impl FnMut(&mut Op) -> zbus::Result<()>,
+    ) -> zbus::Result<()> {
+        while let Some(op) = self.ops.front_mut() {
+            write(op)?;
+            self.ops.pop_front();
+        }
+        Ok(())
+    }
+
+    pub fn succeeded(&mut self) {
+        self.backoff = None;
+        self.retry_at = None;
+    }
+
+    // returns the delay until the next attempt.
+    pub fn failed(&mut self, now: Instant) -> Duration {
+        let backoff = self
+            .backoff
+            .map_or(INITIAL_BACKOFF, |backoff| (backoff * 2).min(MAX_BACKOFF));
+        self.backoff = Some(backoff);
+        self.retry_at = Some(now + backoff);
+        backoff
+    }
+}
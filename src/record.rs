@@ -0,0 +1,160 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    commands,
+    config::{check_range, BRIGHTNESS_RANGE, GAMMA_RANGE, TEMPERATURE_RANGE},
+    daemon::Capabilities,
+    pending::Op,
+    worker::{write_value, DaemonState},
+    AppletError, GammaRelayProxyBlocking,
+};
+
+// one line of a recording: seconds since it started, then the values the daemon
+// had, e.g. "1.250 temperature=4500 brightness=0.8000 gamma=1.0000 inverted=false".
+// values left out of a line written by hand are not replayed.
+#[derive(Debug, Default, PartialEq)]
+pub struct Entry {
+    pub at: Duration,
+    pub temperature: Option<u16>,
+    pub brightness: Option<f64>,
+    pub gamma: Option<f64>,
+    pub inverted: Option<bool>,
+}
+
+impl Entry {
+    fn format(at: Duration, state: &DaemonState) -> String {
+        format!(
+            "{:.3} temperature={} brightness={:.4} gamma={:.4} inverted={}",
+            at.as_secs_f64(),
+            state.temperature,
+            state.brightness,
+            state.gamma,
+            state.inverted
+        )
+    }
+
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let at = words.next().unwrap_or_default();
+        let at = at
+            .parse::<f64>()
+            .ok()
+            .filter(|at| at.is_finite() && *at >= 0.0)
+            .ok_or_else(|| format!("\"{at}\" is not a time in seconds"))?;
+        let mut entry = Self {
+            at: Duration::from_secs_f64(at),
+            ..Self::default()
+        };
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("\"{word}\" is not like key=value"))?;
+            let invalid = |e: &dyn std::fmt::Display| format!("{key}: {e}");
+            match key {
+                "temperature" => {
+                    let value = value.parse().map_err(|e| invalid(&e))?;
+                    check_range(key, value, &TEMPERATURE_RANGE)?;
+                    entry.temperature = Some(value);
+                }
+                "brightness" => {
+                    let value = value.parse().map_err(|e| invalid(&e))?;
+                    check_range(key, value, &BRIGHTNESS_RANGE)?;
+                    entry.brightness = Some(value);
+                }
+                "gamma" => {
+                    let value = value.parse().map_err(|e| invalid(&e))?;
+                    check_range(key, value, &GAMMA_RANGE)?;
+                    entry.gamma = Some(value);
+                }
+                "inverted" => entry.inverted = Some(value.parse().map_err(|e| invalid(&e))?),
+                _ => return Err(format!("unknown value \"{key}\"")),
+            }
+        }
+        Ok(entry)
+    }
+}
+
+// write a line to `path` with the daemon values now and each time they change,
+// by the applet or anything else. runs until the daemon's connection closes,
+// so it belongs on its own thread.
+pub fn record(proxy: &GammaRelayProxyBlocking, path: &Path) -> Result<(), AppletError> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let start = Instant::now();
+    let mut result = Ok(());
+    commands::follow(proxy, |_, state| {
+        // flushed at once, so the recording is complete whenever the applet ends.
+        if result.is_ok() {
+            result = writeln!(file, "{}", Entry::format(start.elapsed(), state))
+                .and_then(|_| file.flush());
+        }
+    })?;
+    Ok(result?)
+}
+
+// write the values of a recording to the daemon, each at its time from now.
+// the whole file is read first, so a mistake in it stops nothing halfway.
+pub fn replay(proxy: &GammaRelayProxyBlocking, path: &Path) -> Result<(), AppletError> {
+    let text = std::fs::read_to_string(path)?;
+    let entries = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            Entry::parse(line).map_err(|e| {
+                AppletError::Recording(format!("{}:{}: {e}", path.display(), index + 1))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let capabilities = Capabilities::detect(proxy)?;
+    let mut state = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: proxy.temperature()?,
+        brightness: proxy.brightness()?,
+        gamma: 1.0,
+    };
+    let start = Instant::now();
+    for entry in entries {
+        if let Some(wait) = entry.at.checked_sub(start.elapsed()) {
+            std::thread::sleep(wait);
+        }
+        let ops = [
+            entry.temperature.map(Op::Temperature),
+            entry.brightness.map(Op::Brightness),
+            entry
+                .gamma
+                .filter(|_| capabilities.supports_control("gamma"))
+                .map(Op::Gamma),
+            entry
+                .inverted
+                .filter(|_| capabilities.supports_control("invert"))
+                .map(Op::Invert),
+        ];
+        for op in ops.into_iter().flatten() {
+            write_value(proxy, &mut state, op)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_lines_are_parsed_and_checked() {
+        let entry = Entry::parse("1.250 temperature=4500 inverted=true").expect("rust: valid line");
+        assert_eq!(entry.at, Duration::from_millis(1250));
+        assert_eq!(entry.temperature, Some(4500));
+        assert_eq!(entry.brightness, None);
+        assert_eq!(entry.inverted, Some(true));
+        assert!(Entry::parse("0 temperature=500").is_err());
+        assert!(Entry::parse("soon brightness=0.5").is_err());
+    }
+}
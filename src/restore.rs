@@ -0,0 +1,150 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::spawn,
+};
+
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{
+    create_proxy,
+    pending::Op,
+    worker::{write_value, DaemonState},
+};
+
+// which values --restore-on-exit writes back.
+#[derive(Clone, Copy, Debug, clap::ValueEnum, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RestoreMode {
+    // the daemon values found at startup.
+    Startup,
+    // the --default-* values, not inverted.
+    Defaults,
+}
+
+// daemon values written back when the applet exits, panics or is killed, so a
+// mishap cannot leave the screen inverted at 1000 K. they are written once.
+#[derive(Clone)]
+pub struct Restore {
+    output: Option<String>,
+    ops: Vec<Op>,
+    done: Arc<AtomicBool>,
+}
+
+impl Restore {
+    // older daemons lack invert, gamma and grayscale, so those are left alone there.
+    pub fn new(
+        output: Option<&str>,
+        state: DaemonState,
+        has_invert: bool,
+        has_gamma: bool,
+        has_grayscale: bool,
+    ) -> Self {
+        let mut ops = vec![
+            Op::Temperature(state.temperature),
+            Op::Brightness(state.brightness),
+        ];
+        if has_invert {
+            ops.push(Op::Invert(state.inverted));
+        }
+        if has_gamma {
+            ops.push(Op::Gamma(state.gamma));
+        }
+        if has_grayscale {
+            ops.push(Op::Grayscale(state.grayscale));
+        }
+        Self {
+            output: output.map(String::from),
+            ops,
+            done: Arc::default(),
+        }
+    }
+
+    // write the values back on a fresh connection, as this may run from a panic
+    // hook or an async task, where the worker and blocking calls are unusable.
+    pub fn run(&self) {
+        if self.done.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let output = self.output.clone();
+        let ops = self.ops.clone();
+        let result = spawn(move || {
+            let proxy = create_proxy(output.as_deref())?;
+            let mut state = DaemonState {
+                inverted: false,
+                grayscale: false,
+                temperature: 0,
+                brightness: 0.0,
+                gamma: 0.0,
+            };
+            for op in ops {
+                write_value(&proxy, &mut state, op)?;
+            }
+            Ok::<_, crate::AppletError>(())
+        })
+        .join();
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("rust: restore daemon values: {e}"),
+            Err(_) => eprintln!("rust: restore daemon values: thread panicked"),
+        }
+    }
+
+    // restore after the default panic message, and on SIGINT, SIGTERM and
+    // SIGHUP before exiting with the usual 128 + signal number.
+    pub fn install(&self, runtime: &tokio::runtime::Runtime) -> std::io::Result<()> {
+        let hook = std::panic::take_hook();
+        let restore = self.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            hook(info);
+            restore.run();
+        }));
+
+        let _guard = runtime.enter();
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        let mut hangup = signal(SignalKind::hangup())?;
+        let restore = self.clone();
+        runtime.spawn(async move {
+            let number = tokio::select! {
+                _ = interrupt.recv() => libc_signal::SIGINT,
+                _ = terminate.recv() => libc_signal::SIGTERM,
+                _ = hangup.recv() => libc_signal::SIGHUP,
+            };
+            restore.run();
+            std::process::exit(128 + number);
+        });
+        Ok(())
+    }
+}
+
+// signal numbers, which are the same on every platform wayland runs on.
+mod libc_signal {
+    pub const SIGHUP: i32 = 1;
+    pub const SIGINT: i32 = 2;
+    pub const SIGTERM: i32 = 15;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: DaemonState = DaemonState {
+        inverted: false,
+        grayscale: false,
+        temperature: 6500,
+        brightness: 1.0,
+        gamma: 1.0,
+    };
+
+    #[test]
+    fn grayscale_is_restored_where_the_daemon_has_it() {
+        let restore = Restore::new(None, STATE, true, true, true);
+        assert!(restore.ops.contains(&Op::Grayscale(false)));
+        assert!(restore.ops.contains(&Op::Invert(false)));
+        let restore = Restore::new(None, STATE, false, false, false);
+        assert_eq!(restore.ops, [Op::Temperature(6500), Op::Brightness(1.0)]);
+    }
+}
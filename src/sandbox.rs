@@ -0,0 +1,175 @@
+use std::{path::PathBuf, process::Command, sync::OnceLock};
+
+// present in every flatpak sandbox, listing the app id and its permissions.
+const FLATPAK_INFO: &str = "/.flatpak-info";
+
+// environment the host tools launched from the sandbox need.
+const FORWARDED_ENV: [&str; 3] = ["WAYLAND_DISPLAY", "SWAYSOCK", "HYPRLAND_INSTANCE_SIGNATURE"];
+
+// bus names the applet uses, the access it needs, and what for. portals are
+// always reachable from a flatpak, so they are not listed.
+pub const SESSION_NAMES: [(&str, &str, &str); 5] = [
+    ("rs.wl-gammarelay", "talk", "controlling the daemon"),
+    (
+        "rs.wl-gammarelay-applet",
+        "own",
+        "the applet's dbus interface and --daemonize",
+    ),
+    (
+        "rs.wl-gammarelay-applet.*",
+        "own",
+        "the dbus interface with --output and --per-output",
+    ),
+    (
+        "org.freedesktop.Flatpak",
+        "talk",
+        "running swaymsg, hyprctl and ddcutil on the host",
+    ),
+    (
+        "nl.whynothugo.darkman",
+        "talk",
+        "following darkman with [theme] darkman",
+    ),
+];
+pub const SYSTEM_NAMES: [(&str, &str, &str); 1] =
+    [("org.freedesktop.login1", "talk", "the --backlight slider")];
+
+// the sandbox the applet runs in, detected under flatpak or forced with --sandbox.
+pub struct Sandbox {
+    // the flatpak application id, unless forced outside flatpak.
+    pub app_id: Option<String>,
+    // sections of the flatpak info file, as (section, key, value).
+    info: Vec<(String, String, String)>,
+}
+
+static SANDBOX: OnceLock<Option<Sandbox>> = OnceLock::new();
+
+// detect the sandbox once at startup; `forced` treats any environment as one.
+pub fn init(forced: bool) {
+    let _ = SANDBOX.set(Sandbox::detect(forced));
+}
+
+pub fn current() -> Option<&'static Sandbox> {
+    SANDBOX.get_or_init(|| Sandbox::detect(false)).as_ref()
+}
+
+// a command for a program on the host. from a sandbox it runs through
+// flatpak-spawn, as the sandbox has neither the program nor the compositor socket.
+pub fn host_command(program: &str) -> Command {
+    if current().is_none() {
+        return Command::new(program);
+    }
+    let mut command = Command::new("flatpak-spawn");
+    command.arg("--host");
+    for name in FORWARDED_ENV {
+        if let Some(value) = std::env::var_os(name) {
+            command.arg(format!("--env={name}={}", value.to_string_lossy()));
+        }
+    }
+    command.arg(program);
+    command
+}
+
+// where a program is on the host, looked up by the host's shell when sandboxed.
+pub fn find_program(program: &str) -> Option<PathBuf> {
+    if current().is_some() {
+        let output = host_command("sh")
+            .args(["-c", "command -v \"$0\"", program])
+            .output()
+            .ok()?;
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path));
+    }
+    std::env::var_os("PATH").and_then(|path| {
+        std::env::split_paths(&path)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+// the command line the host starts the applet with, e.g. from a dbus activation
+// file. a path inside the sandbox means nothing outside it.
+pub fn launch_command() -> std::io::Result<String> {
+    match current().and_then(|sandbox| sandbox.app_id.as_deref()) {
+        Some(app_id) => Ok(format!("flatpak run {app_id}")),
+        None => Ok(std::env::current_exe()?.display().to_string()),
+    }
+}
+
+// the host's data directory. a flatpak's XDG_DATA_HOME is private to the app.
+pub fn host_data_home() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"));
+    if current().is_some() {
+        return std::env::var_os("HOST_XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(home);
+    }
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(home)
+}
+
+// the host's config directory, where systemd looks for user units.
+pub fn host_config_home() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"));
+    if current().is_some() {
+        return std::env::var_os("HOST_XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(home);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(home)
+}
+
+impl Sandbox {
+    fn detect(forced: bool) -> Option<Self> {
+        let info = std::fs::read_to_string(FLATPAK_INFO)
+            .map(|text| parse_info(&text))
+            .unwrap_or_default();
+        let app_id = info
+            .iter()
+            .find(|(section, key, _)| section == "Application" && key == "name")
+            .map(|(_, _, value)| value.clone())
+            .or_else(|| std::env::var("FLATPAK_ID").ok());
+        (forced || app_id.is_some()).then_some(Self { app_id, info })
+    }
+
+    // whether the permissions are known, which they are only under flatpak.
+    pub fn knows_permissions(&self) -> bool {
+        !self.info.is_empty()
+    }
+
+    // whether a bus policy grants `access` to `name`. access levels grow from
+    // see to talk to own, and a policy for "prefix.*" covers names below it.
+    pub fn allows(&self, system: bool, name: &str, access: &str) -> bool {
+        let section = if system {
+            "System Bus Policy"
+        } else {
+            "Session Bus Policy"
+        };
+        let level = |access: &str| ["see", "talk", "own"].iter().position(|a| *a == access);
+        self.info.iter().any(|(s, policy_name, granted)| {
+            let covers = policy_name == name
+                || policy_name.strip_suffix(".*").is_some_and(|prefix| {
+                    let name = name.strip_suffix(".*").unwrap_or(name);
+                    name == prefix || name.starts_with(&format!("{prefix}."))
+                });
+            s == section && covers && level(granted) >= level(access)
+        })
+    }
+}
+
+// the keyfile format of /.flatpak-info: [section] headers and key=value lines.
+fn parse_info(text: &str) -> Vec<(String, String, String)> {
+    let mut section = String::new();
+    let mut entries = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            entries.push((section.clone(), key.to_string(), value.to_string()));
+        }
+    }
+    entries
+}
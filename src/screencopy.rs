@@ -0,0 +1,185 @@
+use std::{
+    os::{fd::AsFd, unix::fs::FileExt},
+    time::Duration,
+};
+
+use wayland_client::{
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_buffer, wl_output, wl_registry, wl_shm, wl_shm_pool},
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+};
+
+use crate::{wayland, AppletError};
+
+// the buffer the compositor asks for and how far the copy has come.
+#[derive(Default)]
+struct Frame {
+    // format, width, height and stride.
+    buffer: Option<(wl_shm::Format, u32, u32, u32)>,
+    buffer_done: bool,
+    ready: bool,
+    failed: bool,
+}
+
+struct State {
+    frame: Frame,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer {
+                format: WEnum::Value(format),
+                width,
+                height,
+                stride,
+            } => {
+                state.frame.buffer = Some((format, width, height, stride));
+                // before version 3 the shm buffer is the only one offered.
+                state.frame.buffer_done |= frame.version() < 3;
+            }
+            zwlr_screencopy_frame_v1::Event::BufferDone => state.frame.buffer_done = true,
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => state.frame.ready = true,
+            zwlr_screencopy_frame_v1::Event::Failed => state.frame.failed = true,
+            _ => {}
+        }
+    }
+}
+
+delegate_noop!(State: ignore wl_shm::WlShm);
+delegate_noop!(State: ignore wl_buffer::WlBuffer);
+delegate_noop!(State: ignore wl_output::WlOutput);
+delegate_noop!(State: wl_shm_pool::WlShmPool);
+delegate_noop!(State: ZwlrScreencopyManagerV1);
+
+// average luma of a frame, from 0.0 for black to 1.0 for white. every 8th
+// pixel of every 8th row is plenty for an average.
+fn luminance(
+    data: &[u8],
+    format: wl_shm::Format,
+    width: u32,
+    height: u32,
+    stride: u32,
+) -> Option<f64> {
+    // 32-bit formats are little-endian, so xrgb is stored as b, g, r, x.
+    let (r, g, b) = match format {
+        wl_shm::Format::Xrgb8888 | wl_shm::Format::Argb8888 => (2, 1, 0),
+        wl_shm::Format::Xbgr8888 | wl_shm::Format::Abgr8888 => (0, 1, 2),
+        _ => return None,
+    };
+    let mut sum = 0.0;
+    let mut count = 0;
+    for y in (0..height as usize).step_by(8) {
+        for x in (0..width as usize).step_by(8) {
+            let pixel = data.get(y * stride as usize + x * 4..)?.get(..4)?;
+            sum += 0.2126 * pixel[r] as f64 + 0.7152 * pixel[g] as f64 + 0.0722 * pixel[b] as f64;
+            count += 1;
+        }
+    }
+    (count > 0).then(|| sum / count as f64 / 255.0)
+}
+
+// copy one output and return its average luma, or None if the compositor
+// refuses or offers no format this reads.
+fn sample(
+    queue: &mut EventQueue<State>,
+    state: &mut State,
+    manager: &ZwlrScreencopyManagerV1,
+    shm: &wl_shm::WlShm,
+    output: &wl_output::WlOutput,
+) -> Result<Option<f64>, AppletError> {
+    state.frame = Frame::default();
+    let frame = manager.capture_output(0, output, &queue.handle(), ());
+    while !state.frame.buffer_done && !state.frame.failed {
+        queue.blocking_dispatch(state)?;
+    }
+    let Some((format, width, height, stride)) = state.frame.buffer.filter(|_| !state.frame.failed)
+    else {
+        frame.destroy();
+        return Ok(None);
+    };
+    let size = stride as u64 * height as u64;
+    let file = wayland::shm_file("screencopy", size)?;
+    let pool = shm.create_pool(file.as_fd(), size as i32, &queue.handle(), ());
+    let buffer = pool.create_buffer(
+        0,
+        width as i32,
+        height as i32,
+        stride as i32,
+        format,
+        &queue.handle(),
+        (),
+    );
+    frame.copy(&buffer);
+    while !state.frame.ready && !state.frame.failed {
+        queue.blocking_dispatch(state)?;
+    }
+    let value = if state.frame.ready {
+        let mut data = vec![0; size as usize];
+        file.read_exact_at(&mut data, 0)?;
+        luminance(&data, format, width, height, stride)
+    } else {
+        None
+    };
+    frame.destroy();
+    buffer.destroy();
+    pool.destroy();
+    Ok(value)
+}
+
+// call `sampled` every `interval` with the average luma of all outputs. runs
+// until the wayland connection closes, so it belongs on its own thread. needs
+// a compositor with wlr-screencopy, such as sway or hyprland.
+pub fn watch(interval: Duration, mut sampled: impl FnMut(f64)) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+    let qhandle = queue.handle();
+    let manager: ZwlrScreencopyManagerV1 = globals.bind(&qhandle, 1..=3, ())?;
+    let shm: wl_shm::WlShm = globals.bind(&qhandle, 1..=1, ())?;
+    let outputs: Vec<wl_output::WlOutput> = globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .filter(|global| global.interface == "wl_output")
+        .map(|global| globals.registry().bind(global.name, 1, &qhandle, ()))
+        .collect();
+    let mut state = State {
+        frame: Frame::default(),
+    };
+    loop {
+        let mut values = Vec::new();
+        for output in &outputs {
+            if let Some(value) = sample(&mut queue, &mut state, &manager, &shm, output)? {
+                values.push(value);
+            }
+        }
+        if !values.is_empty() {
+            sampled(values.iter().sum::<f64>() / values.len() as f64);
+        }
+        std::thread::sleep(interval);
+    }
+}
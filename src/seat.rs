@@ -0,0 +1,112 @@
+use std::sync::OnceLock;
+
+use zbus::{
+    blocking::{Connection, ConnectionBuilder},
+    dbus_proxy,
+    zvariant::OwnedObjectPath,
+};
+
+use crate::AppletError;
+
+// # DBus interface proxies for: `org.freedesktop.login1`
+// Only what is needed to find the session bus of a seat's active session is declared.
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Manager",
+    default_path = "/org/freedesktop/login1"
+)]
+trait LoginManager {
+    /// GetSeat method
+    fn get_seat(&self, seat_id: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Seat"
+)]
+trait LoginSeat {
+    /// ActiveSession property
+    #[dbus_proxy(property)]
+    fn active_session(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Session"
+)]
+trait LoginSessionUser {
+    /// User property
+    #[dbus_proxy(property)]
+    fn user(&self) -> zbus::Result<(u32, OwnedObjectPath)>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.User"
+)]
+trait LoginUser {
+    /// RuntimePath property
+    #[dbus_proxy(property)]
+    fn runtime_path(&self) -> zbus::Result<String>;
+}
+
+// the seat chosen with --seat, whose daemon is controlled instead of the one
+// on this session's bus.
+static SEAT: OnceLock<Option<String>> = OnceLock::new();
+
+pub fn init(seat: Option<String>) {
+    let _ = SEAT.set(seat);
+}
+
+// a connection to the bus the daemon is on: the session bus of the active
+// session on the chosen seat, or this session's bus.
+pub fn daemon_connection() -> Result<Connection, AppletError> {
+    let Some(seat) = SEAT.get().and_then(Option::as_deref) else {
+        return Ok(Connection::session()?);
+    };
+    Ok(ConnectionBuilder::address(bus_address(seat)?.as_str())?.build()?)
+}
+
+// which bus the daemon is reached on, for the about panel.
+pub fn bus_description() -> String {
+    match SEAT.get().and_then(Option::as_deref) {
+        Some(seat) => format!("session bus of {seat}"),
+        None => match std::env::var("DBUS_SESSION_BUS_ADDRESS") {
+            Ok(address) => format!("session bus {address}"),
+            Err(_) => "session bus".to_string(),
+        },
+    }
+}
+
+// ask logind who is active on the seat, and find their session bus in their
+// runtime directory. the bus only lets its own user in, so the seat must be
+// in use by the user running the applet.
+pub fn bus_address(seat: &str) -> Result<String, AppletError> {
+    let system = Connection::system()?;
+    let seat_path = LoginManagerProxyBlocking::new(&system)?
+        .get_seat(seat)
+        .map_err(|e| AppletError::Seat(format!("{seat}: {e}")))?;
+    let (session, session_path) = LoginSeatProxyBlocking::builder(&system)
+        .path(seat_path)?
+        .build()?
+        .active_session()?;
+    if session.is_empty() {
+        return Err(AppletError::Seat(format!("{seat}: no active session")));
+    }
+    let (uid, user_path) = LoginSessionUserProxyBlocking::builder(&system)
+        .path(session_path)?
+        .build()?
+        .user()?;
+    let runtime_path = LoginUserProxyBlocking::builder(&system)
+        .path(user_path)?
+        .build()?
+        .runtime_path()?;
+    // SAFETY: getuid cannot fail and has no preconditions.
+    let own_uid = unsafe { libc::getuid() };
+    if uid != own_uid {
+        return Err(AppletError::Seat(format!(
+            "{seat}: session {session} belongs to uid {uid}, not {own_uid}"
+        )));
+    }
+    Ok(format!("unix:path={runtime_path}/bus"))
+}
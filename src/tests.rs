@@ -0,0 +1,717 @@
+use std::{cell::RefCell, rc::Rc};
+
+use slint::{platform::WindowEvent, ComponentHandle};
+
+use crate::{
+    bench::{headless_applet, tick},
+    config::Preset,
+    convert::dbus_temperature_to_ui_value,
+    count_down_guard,
+    worker::{mock::MockWorker, DaemonState},
+    Commit, Parameters, Reconcile, Shared, Startup, WlGammaRelayApplet,
+};
+
+// 5500 K is the middle of the temperature slider, so slider values are exact.
+const START: DaemonState = DaemonState {
+    inverted: false,
+    grayscale: false,
+    temperature: 5500,
+    brightness: 0.8,
+    gamma: 1.0,
+};
+
+// an applet wired up as in main, talking to a mock daemon.
+struct Fixture {
+    app: WlGammaRelayApplet,
+    shared: Shared,
+    worker: MockWorker,
+}
+
+impl Fixture {
+    fn new(daemon: DaemonState) -> Self {
+        let (app, shared, worker) = headless_applet(daemon).expect("rust: create applet");
+        Fixture {
+            app,
+            shared,
+            worker,
+        }
+    }
+
+    // run ticks until the worker has nothing left to answer, as the timer would,
+    // and return the daemon calls made.
+    fn settle(&mut self) -> Vec<String> {
+        for _ in 0..10 {
+            tick(&self.app, &self.shared, &mut self.worker);
+        }
+        self.worker.calls()
+    }
+
+    fn daemon(&self) -> DaemonState {
+        *self.worker.daemon.state.borrow()
+    }
+
+    fn parameters(&self) -> Parameters<'_> {
+        self.app.global::<Parameters>()
+    }
+}
+
+#[test]
+fn temperature_slider_writes_whole_steps_of_the_difference() {
+    let mut fixture = Fixture::new(START);
+    // -2250 K is applied as -2200 K; the rest is kept for the next move.
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.25);
+    assert_eq!(fixture.settle(), ["UpdateTemperature(-2200)"]);
+    assert_eq!(fixture.daemon().temperature, 3300);
+    assert_eq!(fixture.parameters().get_value_text(), "3300 K");
+}
+
+#[test]
+fn small_slider_moves_accumulate_until_they_make_a_step() {
+    let mut fixture = Fixture::new(START);
+    let mut calls = Vec::new();
+    for temperature in [5470, 5440, 5410, 5380] {
+        fixture.parameters().invoke_slider_changed(
+            "temperature".into(),
+            dbus_temperature_to_ui_value(temperature) as f32,
+        );
+        calls.extend(fixture.settle());
+    }
+    assert_eq!(calls, ["UpdateTemperature(-100)"]);
+    assert_eq!(fixture.daemon().temperature, 5400);
+}
+
+#[test]
+fn temperature_steps_are_clamped_to_the_daemon_range() {
+    let mut fixture = Fixture::new(DaemonState {
+        temperature: 9950,
+        ..START
+    });
+    fixture
+        .parameters()
+        .invoke_slider_step("temperature".into(), 1);
+    assert_eq!(fixture.settle(), ["UpdateTemperature(50)"]);
+    assert_eq!(fixture.daemon().temperature, 10000);
+}
+
+#[test]
+fn a_fast_drag_is_written_once_the_slider_settles() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.settings.borrow_mut().settle = std::time::Duration::from_millis(50);
+    for value in [0.45, 0.35, 0.25] {
+        fixture
+            .parameters()
+            .invoke_slider_changed("temperature".into(), value);
+        assert!(fixture.settle().is_empty());
+    }
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    assert_eq!(fixture.settle(), ["UpdateTemperature(-2200)"]);
+    assert_eq!(fixture.daemon().temperature, 3300);
+}
+
+#[test]
+fn commit_on_release_writes_once_the_slider_is_let_go() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.settings.borrow_mut().commit = Commit::OnRelease;
+    fixture
+        .parameters()
+        .invoke_slider_held("temperature".into(), true);
+    for value in [0.45, 0.25] {
+        fixture
+            .parameters()
+            .invoke_slider_changed("temperature".into(), value);
+        assert!(fixture.settle().is_empty());
+    }
+    assert_eq!(fixture.parameters().get_value_text(), "3250 K");
+    fixture
+        .parameters()
+        .invoke_slider_held("temperature".into(), false);
+    assert_eq!(fixture.settle(), ["UpdateTemperature(-2200)"]);
+}
+
+#[test]
+fn brightness_slider_writes_rounded_deltas() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .parameters()
+        .invoke_slider_changed("brightness".into(), 0.5);
+    assert_eq!(fixture.settle(), ["UpdateBrightness(-0.30)"]);
+    assert!((fixture.daemon().brightness - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn gamma_slider_writes_rounded_deltas() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .parameters()
+        .invoke_slider_changed("gamma".into(), 0.75);
+    assert_eq!(fixture.settle(), ["UpdateGamma(0.25)"]);
+    assert!((fixture.daemon().gamma - 1.25).abs() < 1e-9);
+}
+
+#[test]
+fn invert_switch_toggles_the_daemon_once() {
+    let mut fixture = Fixture::new(START);
+    fixture.parameters().invoke_invert_changed(true);
+    fixture.parameters().invoke_invert_changed(true);
+    assert_eq!(fixture.settle(), ["ToggleInverted"]);
+    assert!(fixture.daemon().inverted);
+    assert!(fixture.parameters().get_invert());
+}
+
+#[test]
+fn invert_switch_follows_the_daemon() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .worker
+        .change_externally(|state| state.inverted = true);
+    fixture.settle();
+    assert!(fixture.parameters().get_invert());
+    fixture.parameters().invoke_invert_changed(false);
+    assert_eq!(fixture.settle(), ["ToggleInverted"]);
+    assert!(!fixture.daemon().inverted);
+    assert!(!fixture.parameters().get_invert());
+}
+
+#[test]
+fn right_click_resets_a_slider_to_its_default() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.25);
+    fixture.settle();
+    fixture
+        .parameters()
+        .invoke_slider_default("temperature".into());
+    assert_eq!(fixture.settle(), ["UpdateTemperature(2200)"]);
+    assert_eq!(fixture.daemon().temperature, 5500);
+}
+
+#[test]
+fn scrolling_a_slider_changes_the_daemon() {
+    let mut fixture = Fixture::new(START);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    // the first slider, temperature by default, fills the middle of the left half.
+    let position = slint::LogicalPosition::new(25.0, 110.0);
+    window.dispatch_event(WindowEvent::PointerMoved { position });
+    window.dispatch_event(WindowEvent::PointerScrolled {
+        position,
+        delta_x: 0.0,
+        delta_y: -1.0,
+    });
+    // one notch is 2.5% of the range, 225 K, applied as 200 K.
+    assert_eq!(fixture.settle(), ["UpdateTemperature(-200)"]);
+    assert_eq!(fixture.daemon().temperature, 5300);
+}
+
+#[test]
+fn undo_and_redo_a_slider_move() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.25);
+    fixture.settle();
+    assert!(fixture.parameters().get_can_undo());
+    fixture.parameters().invoke_undo();
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 5500);
+    assert!(!fixture.parameters().get_can_undo());
+    fixture.parameters().invoke_redo();
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 3300);
+}
+
+#[test]
+fn window_fades_only_after_the_pointer_stays_away() {
+    let fixture = Fixture::new(START);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    let inside = slint::LogicalPosition::new(50.0, 50.0);
+    let opacity = || fixture.parameters().get_window_opacity();
+    window.dispatch_event(WindowEvent::PointerMoved { position: inside });
+    fixture.app.invoke_manage_opacity(1000.0);
+    assert_eq!(opacity(), 1.0);
+    // leaving and coming back within the 500 ms grace period starts it over.
+    window.dispatch_event(WindowEvent::PointerExited);
+    fixture.app.invoke_manage_opacity(300.0);
+    window.dispatch_event(WindowEvent::PointerMoved { position: inside });
+    fixture.app.invoke_manage_opacity(10.0);
+    // the window keeps from fading while it has keyboard focus.
+    window.dispatch_event(WindowEvent::PointerExited);
+    window.dispatch_event(WindowEvent::WindowActiveChanged(false));
+    fixture.app.invoke_manage_opacity(300.0);
+    assert_eq!(opacity(), 1.0);
+    fixture.app.invoke_manage_opacity(300.0);
+    assert!(opacity() < 1.0);
+}
+
+#[test]
+fn temporary_preset_is_reverted_when_it_ends() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.config.borrow_mut().presets.insert(
+        "night".to_string(),
+        Preset {
+            temperature: Some(3000),
+            brightness: None,
+            gamma: None,
+            inverted: None,
+        },
+    );
+    fixture
+        .parameters()
+        .invoke_temporary_preset("night".into(), 90);
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 3000);
+    assert!(fixture.parameters().get_timer_active());
+    fixture.parameters().invoke_temporary_preset("".into(), 0);
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 5500);
+    assert!(!fixture.parameters().get_timer_active());
+}
+
+#[test]
+fn read_only_window_ignores_the_pointer() {
+    let mut fixture = Fixture::new(START);
+    fixture.app.global::<Startup>().set_read_only(true);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    let position = slint::LogicalPosition::new(25.0, 110.0);
+    window.dispatch_event(WindowEvent::PointerMoved { position });
+    window.dispatch_event(WindowEvent::PointerScrolled {
+        position,
+        delta_x: 0.0,
+        delta_y: -1.0,
+    });
+    window.dispatch_event(WindowEvent::PointerPressed {
+        position,
+        button: slint::platform::PointerEventButton::Left,
+    });
+    window.dispatch_event(WindowEvent::PointerReleased {
+        position,
+        button: slint::platform::PointerEventButton::Left,
+    });
+    assert!(fixture.settle().is_empty());
+    assert_eq!(fixture.daemon(), START);
+}
+
+#[test]
+fn hover_tips_show_exact_values_and_ranges() {
+    let fixture = Fixture::new(START);
+    let tip = |name: &str, value: f32| {
+        fixture
+            .parameters()
+            .invoke_exact_value(name.into(), value)
+            .to_string()
+    };
+    assert_eq!(tip("temperature", 0.0), "1000 K");
+    assert_eq!(tip("temperature", 1.0), "10000 K");
+    assert_eq!(tip("temperature", 0.7), "7300 K");
+    assert_eq!(tip("brightness", 0.853), "85.3 %");
+    assert_eq!(tip("gamma", 0.0), "0.5 γ");
+    assert_eq!(tip("gamma", 0.737), "1.237 γ");
+    assert_eq!(tip("ddc:0", 1.0), "100 %");
+}
+
+#[test]
+fn extreme_values_are_reverted_unless_kept() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.config.borrow_mut().guard.enabled = true;
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.0);
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 1000);
+    assert_eq!(fixture.parameters().get_confirm_seconds(), 10);
+    for _ in 0..10 {
+        count_down_guard(&fixture.shared);
+    }
+    fixture.settle();
+    assert_eq!(fixture.daemon(), START);
+    assert_eq!(fixture.parameters().get_confirm_seconds(), 0);
+
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.0);
+    fixture.settle();
+    fixture.parameters().invoke_keep_values();
+    count_down_guard(&fixture.shared);
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 1000);
+    assert_eq!(fixture.parameters().get_confirm_seconds(), 0);
+}
+
+#[test]
+fn large_change_is_kept_by_another_change() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.safety_revert = Some(15);
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.0);
+    fixture.settle();
+    assert_eq!(fixture.parameters().get_confirm_seconds(), 15);
+    // the drag ended a while ago, and the next scroll is a change of its own.
+    fixture.shared.history.borrow_mut().end_change();
+    fixture
+        .parameters()
+        .invoke_slider_changed("temperature".into(), 0.1);
+    fixture.settle();
+    assert_eq!(fixture.parameters().get_confirm_seconds(), 0);
+    count_down_guard(&fixture.shared);
+    fixture.settle();
+    assert_eq!(fixture.daemon().temperature, 1900);
+}
+
+#[test]
+fn arrow_keys_step_the_selected_slider() {
+    let mut fixture = Fixture::new(START);
+    let position = slint::LogicalPosition::new(25.0, 110.0);
+    {
+        let window = fixture.app.window();
+        window.set_size(slint::PhysicalSize::new(100, 220));
+        window.dispatch_event(WindowEvent::PointerMoved { position });
+        window.dispatch_event(WindowEvent::PointerScrolled {
+            position,
+            delta_x: 0.0,
+            delta_y: -1.0,
+        });
+    }
+    assert_eq!(fixture.settle(), ["UpdateTemperature(-200)"]);
+    assert_eq!(fixture.parameters().get_selected_slider(), 0);
+    // hovering gives the window keyboard focus.
+    fixture.app.invoke_manage_opacity(10.0);
+    for key in [
+        slint::platform::Key::RightArrow,
+        slint::platform::Key::DownArrow,
+    ] {
+        let window = fixture.app.window();
+        window.dispatch_event(WindowEvent::KeyPressed { text: key.into() });
+        window.dispatch_event(WindowEvent::KeyReleased { text: key.into() });
+    }
+    assert_eq!(fixture.parameters().get_selected_slider(), 1);
+    assert_eq!(fixture.settle(), ["UpdateBrightness(-0.01)"]);
+    assert_eq!(fixture.daemon().temperature, 5300);
+}
+
+#[test]
+fn about_panel_keeps_the_last_error() {
+    let fixture = Fixture::new(START);
+    assert_eq!(fixture.parameters().get_last_error(), "");
+    fixture.parameters().invoke_apply_preset("missing".into());
+    assert_eq!(
+        fixture.parameters().get_last_error(),
+        "no preset named \"missing\""
+    );
+}
+
+#[test]
+fn presets_switch_invert() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.config.borrow_mut().presets.insert(
+        "oled".to_string(),
+        Preset {
+            inverted: Some(true),
+            ..Preset::default()
+        },
+    );
+    fixture.parameters().invoke_apply_preset("oled".into());
+    fixture.settle();
+    assert!(fixture.daemon().inverted);
+    assert!(fixture.parameters().get_invert());
+    assert_eq!(fixture.daemon().temperature, START.temperature);
+}
+
+#[test]
+fn gamma_toggle_switches_between_its_values() {
+    let mut fixture = Fixture::new(START);
+    fixture.parameters().invoke_toggle_gamma();
+    fixture.settle();
+    assert_eq!(fixture.daemon().gamma, 0.85);
+    fixture.parameters().invoke_toggle_gamma();
+    fixture.settle();
+    assert_eq!(fixture.daemon().gamma, 1.0);
+}
+
+#[test]
+fn effect_selector_keeps_one_filter_on() {
+    let mut fixture = Fixture::new(START);
+    fixture.parameters().invoke_effect_changed(2);
+    fixture.settle();
+    assert!(fixture.daemon().grayscale);
+    assert!(!fixture.daemon().inverted);
+    fixture.parameters().invoke_effect_changed(1);
+    let calls = fixture.settle();
+    assert!(calls.contains(&"SetGrayscale(false)".to_string()));
+    assert!(fixture.daemon().inverted);
+    assert!(!fixture.parameters().get_grayscale());
+}
+
+#[test]
+fn window_state_is_published_when_it_changes() {
+    let fixture = Fixture::new(START);
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let publisher = crate::ipc::WindowPublisher::new(sender);
+    fixture.parameters().set_window_opacity(0.456);
+    crate::publish_window_state(&fixture.app, &publisher);
+    crate::publish_window_state(&fixture.app, &publisher);
+    fixture.app.global::<Startup>().set_never_fade(true);
+    crate::publish_window_state(&fixture.app, &publisher);
+    let first = receiver.try_recv().expect("rust: first state");
+    assert_eq!(first.opacity, 0.46);
+    assert!(!first.pinned);
+    assert!(receiver.try_recv().expect("rust: pinned state").pinned);
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn background_buttons_run_their_actions() {
+    let mut fixture = Fixture::new(START);
+    fixture.shared.config.borrow_mut().buttons = crate::config::Buttons {
+        middle: Some("invert".to_string()),
+        scroll_up: Some("temperature+".to_string()),
+        right: Some("reset".to_string()),
+        ..Default::default()
+    };
+    fixture
+        .parameters()
+        .invoke_background_button("middle".into());
+    fixture
+        .parameters()
+        .invoke_background_button("scroll_up".into());
+    fixture
+        .parameters()
+        .invoke_background_button("other".into());
+    fixture.settle();
+    assert!(fixture.daemon().inverted);
+    assert!(fixture.daemon().temperature > START.temperature);
+    fixture
+        .parameters()
+        .invoke_background_button("right".into());
+    fixture.settle();
+    assert_eq!(fixture.daemon(), START);
+    assert_eq!(
+        crate::config::ButtonAction::parse("warmth+"),
+        Err("unknown action \"warmth+\"".to_string())
+    );
+}
+
+#[test]
+fn external_changes_rebase_or_drop_unwritten_moves() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .parameters()
+        .invoke_slider_changed("brightness".into(), 0.9);
+    fixture
+        .worker
+        .change_externally(|state| state.brightness = 0.5);
+    fixture.settle();
+    assert!((fixture.daemon().brightness - 0.6).abs() < 1e-9);
+    assert!((fixture.parameters().get_brightness() - 0.6).abs() < 1e-6);
+
+    fixture.shared.settings.borrow_mut().reconcile = Reconcile::Drop;
+    fixture
+        .parameters()
+        .invoke_slider_changed("brightness".into(), 0.7);
+    fixture
+        .worker
+        .change_externally(|state| state.brightness = 0.3);
+    assert!(fixture.settle().is_empty());
+    assert_eq!(fixture.daemon().brightness, 0.3);
+    assert_eq!(fixture.parameters().get_brightness(), 0.3);
+    assert_ne!(fixture.parameters().get_warning_text(), "");
+}
+
+#[test]
+fn backlight_moves_keep_deltas_in_flight() {
+    let mut fixture = Fixture::new(START);
+    fixture
+        .app
+        .global::<Startup>()
+        .set_backlight_available(true);
+    let parameters = fixture.parameters();
+    parameters.invoke_slider_changed("brightness".into(), 0.7);
+    // the first move is written and not yet answered when the next ones come.
+    crate::sync_worker(&fixture.app, &mut fixture.shared.settings.borrow_mut());
+    parameters.invoke_slider_changed("brightness".into(), 0.6);
+    parameters.invoke_slider_changed("backlight".into(), 0.5);
+    assert_eq!(
+        fixture.settle(),
+        ["UpdateBrightness(-0.10)", "UpdateBrightness(-0.10)"]
+    );
+    let settings = fixture.shared.settings.borrow();
+    assert_eq!(settings.backlight.value, 0.5);
+    assert_eq!(settings.backlight.delta_accumulation, 0.0);
+}
+
+#[test]
+fn buttons_take_clicks_at_their_centres() {
+    let fixture = Fixture::new(START);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    let startup = fixture.app.global::<Startup>();
+    startup.set_show_gamma_toggle(true);
+    startup.set_show_timer(true);
+    let parameters = fixture.parameters();
+    parameters.set_can_undo(true);
+
+    // record which button a click reaches, and nothing else.
+    let clicked = Rc::new(RefCell::new(Vec::new()));
+    let record = |name: &'static str| {
+        let clicked = clicked.clone();
+        move || clicked.borrow_mut().push(name)
+    };
+    parameters.on_undo(record("undo"));
+    parameters.on_resync(record("resync"));
+    parameters.on_toggle_gamma(record("toggle-gamma"));
+    let timer = record("timer");
+    parameters.on_temporary_preset(move |_, _| timer());
+    let copy = record("copy-values");
+    parameters.on_copy_values(move |_| copy());
+
+    // with the default padding the controls are 84 px wide from x = 8, and
+    // the value text takes the top 14 px. the row of buttons below it is
+    // centred, its buttons 2 px apart and narrowed evenly when six don't fit.
+    let count = 6.0;
+    let width = f32::min(14.0, (84.0 - 2.0 * (count - 1.0)) / count);
+    let start = 8.0 + (84.0 - width * count - 2.0 * (count - 1.0)) / 2.0;
+    for (index, name) in [
+        "save-preset",
+        "undo",
+        "toggle-gamma",
+        "timer",
+        "copy-values",
+        "resync",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let (x, y) = (start + (width + 2.0) * index as f32 + width / 2.0, 29.0);
+        let position = slint::LogicalPosition::new(x, y);
+        let button = slint::platform::PointerEventButton::Left;
+        window.dispatch_event(WindowEvent::PointerPressed { position, button });
+        window.dispatch_event(WindowEvent::PointerReleased { position, button });
+        if parameters.get_saving_preset() {
+            clicked.borrow_mut().push("save-preset");
+            parameters.set_saving_preset(false);
+        }
+        assert_eq!(clicked.take(), [name], "{name} at {x}, {y}");
+    }
+}
+
+#[test]
+fn about_panel_opens_without_the_value_text() {
+    let fixture = Fixture::new(START);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    fixture.app.global::<Startup>().set_show_value(false);
+    // the row of buttons moves to the top: 💾, 📋, ⟳ and ⓘ, centred.
+    let position = slint::LogicalPosition::new(74.0, 15.0);
+    let button = slint::platform::PointerEventButton::Left;
+    window.dispatch_event(WindowEvent::PointerPressed { position, button });
+    window.dispatch_event(WindowEvent::PointerReleased { position, button });
+    assert!(fixture.parameters().get_show_about());
+}
+
+// flag values are checked as they are parsed, including durations too long
+// to count in seconds or minutes.
+mod args {
+    use clap::FromArgMatches;
+
+    use crate::{args_command, color_arg, hex_color, seconds_arg, temporary_arg, Args};
+
+    #[test]
+    fn durations_in_seconds() {
+        for (text, seconds) in [
+            ("15s", Ok(15)),
+            ("2m", Ok(120)),
+            ("30", Ok(30)),
+            ("15ss", Err(())),
+            ("0s", Err(())),
+            ("11m", Err(())),
+            ("71582789m", Err(())),
+            ("4294967296", Err(())),
+            ("soon", Err(())),
+        ] {
+            assert_eq!(seconds_arg(text).map_err(|_| ()), seconds, "{text}");
+        }
+    }
+
+    #[test]
+    fn temporary_presets() {
+        for (text, minutes) in [
+            ("night:90m", Ok(90)),
+            ("night:1h30m", Ok(90)),
+            ("night:2h", Ok(120)),
+            ("night:45", Ok(45)),
+            ("night:30mmm", Err(())),
+            ("night:1h30mm", Err(())),
+            ("night:0m", Err(())),
+            ("night:25h", Err(())),
+            ("night:71582789h", Err(())),
+            ("night:71582788h4294967295m", Err(())),
+            (":90m", Err(())),
+            ("night", Err(())),
+        ] {
+            let parsed = temporary_arg(text).map(|temporary| temporary.minutes);
+            assert_eq!(parsed.map_err(|_| ()), minutes, "{text}");
+        }
+    }
+
+    #[test]
+    fn colors() {
+        for (text, valid) in [
+            ("#66aaff", true),
+            ("#66aaff80", true),
+            ("66aaff", false),
+            ("#66aaf", false),
+            ("#66aafg", false),
+            ("#ééaaff", false),
+        ] {
+            assert_eq!(color_arg(text).is_ok(), valid, "{text}");
+        }
+        assert_eq!(
+            hex_color("#66aaff80"),
+            Some(slint::Color::from_argb_u8(0x80, 0x66, 0xaa, 0xff))
+        );
+    }
+
+    // flags are read from WL_GAMMARELAY_APPLET_<FLAG> and checked as on the
+    // command line. the names are only used here, so other tests see no change.
+    #[test]
+    fn flags_from_the_environment() {
+        let parse = || {
+            args_command()
+                .try_get_matches_from(["wl-gammarelay-applet"])
+                .and_then(|matches| Args::from_arg_matches(&matches))
+        };
+        std::env::set_var("WL_GAMMARELAY_APPLET_FADE_GRACE_MS", "250");
+        std::env::set_var("WL_GAMMARELAY_APPLET_NEVER_FADE", "true");
+        std::env::set_var("WL_GAMMARELAY_APPLET_NO_ANIMATIONS", "false");
+        let args = parse().expect("rust: valid environment");
+        assert_eq!(args.fade_grace_ms, 250);
+        assert!(args.never_fade);
+        assert!(!args.no_animations);
+        std::env::set_var("WL_GAMMARELAY_APPLET_DEFAULT_TEMPERATURE", "20000");
+        assert!(parse().is_err());
+        for flag in [
+            "FADE_GRACE_MS",
+            "NEVER_FADE",
+            "NO_ANIMATIONS",
+            "DEFAULT_TEMPERATURE",
+        ] {
+            std::env::remove_var(format!("WL_GAMMARELAY_APPLET_{flag}"));
+        }
+    }
+}
+
+#[test]
+fn preset_name_field_opens_under_the_pointer() {
+    let fixture = Fixture::new(START);
+    let window = fixture.app.window();
+    window.set_size(slint::PhysicalSize::new(100, 220));
+    fixture.parameters().set_saving_preset(true);
+    let position = slint::LogicalPosition::new(50.0, 50.0);
+    window.dispatch_event(WindowEvent::PointerMoved { position });
+    assert!(fixture.parameters().get_saving_preset());
+}
@@ -0,0 +1,99 @@
+use zbus::{
+    blocking::{Connection, Proxy},
+    zvariant::OwnedValue,
+};
+
+const DARKMAN_SERVICE: &str = "nl.whynothugo.darkman";
+const DARKMAN_PATH: &str = "/nl/whynothugo/darkman";
+const DARKMAN_INTERFACE: &str = "nl.whynothugo.darkman";
+
+const PORTAL_SERVICE: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const PORTAL_INTERFACE: &str = "org.freedesktop.portal.Settings";
+const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
+const COLOR_SCHEME_KEY: &str = "color-scheme";
+const REDUCED_MOTION_KEY: &str = "reduced-motion";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Light,
+    Dark,
+}
+
+impl Mode {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "light" => Some(Self::Light),
+            "dark" => Some(Self::Dark),
+            _ => None,
+        }
+    }
+
+    // the portal's color-scheme is 1 for dark; 0 (no preference) counts as light.
+    fn from_color_scheme(value: u32) -> Option<Self> {
+        match value {
+            0 | 2 => Some(Self::Light),
+            1 => Some(Self::Dark),
+            _ => None,
+        }
+    }
+}
+
+// where the desktop's light or dark mode is read from.
+#[derive(Clone, Copy, Debug)]
+pub enum Source {
+    Darkman,
+    Portal,
+}
+
+impl Source {
+    // call `switched` each time the mode changes. runs until the connection
+    // closes, so it belongs on its own thread.
+    pub fn watch(self, connection: &Connection, switched: impl FnMut(Mode)) -> zbus::Result<()> {
+        match self {
+            Self::Darkman => watch_darkman(connection, switched),
+            Self::Portal => watch_portal(connection, switched),
+        }
+    }
+}
+
+// whether the portal's reduced-motion setting asks for less animation. portals
+// without the setting answer with an error.
+pub fn reduced_motion(connection: &Connection) -> zbus::Result<bool> {
+    let proxy = Proxy::new(connection, PORTAL_SERVICE, PORTAL_PATH, PORTAL_INTERFACE)?;
+    // Read wraps the value in a second variant.
+    let value: OwnedValue = proxy.call("Read", &(APPEARANCE_NAMESPACE, REDUCED_MOTION_KEY))?;
+    let value = match value.downcast_ref::<zbus::zvariant::Value>() {
+        Some(inner) => OwnedValue::from(inner.clone()),
+        None => value,
+    };
+    Ok(u32::try_from(value).is_ok_and(|value| value == 1))
+}
+
+fn watch_darkman(connection: &Connection, mut switched: impl FnMut(Mode)) -> zbus::Result<()> {
+    let proxy = Proxy::new(connection, DARKMAN_SERVICE, DARKMAN_PATH, DARKMAN_INTERFACE)?;
+    for message in proxy.receive_signal("ModeChanged")? {
+        let mode: String = message.body()?;
+        match Mode::parse(&mode) {
+            Some(mode) => switched(mode),
+            None => eprintln!("rust: unknown darkman mode \"{mode}\""),
+        }
+    }
+    Ok(())
+}
+
+// the xdg-desktop-portal reports every setting change; only color-scheme is used.
+fn watch_portal(connection: &Connection, mut switched: impl FnMut(Mode)) -> zbus::Result<()> {
+    let proxy = Proxy::new(connection, PORTAL_SERVICE, PORTAL_PATH, PORTAL_INTERFACE)?;
+    for message in proxy.receive_signal("SettingChanged")? {
+        let (namespace, key, value): (String, String, OwnedValue) = message.body()?;
+        if namespace != APPEARANCE_NAMESPACE || key != COLOR_SCHEME_KEY {
+            continue;
+        }
+        match u32::try_from(value).ok().and_then(Mode::from_color_scheme) {
+            Some(mode) => switched(mode),
+            None => eprintln!("rust: unknown portal color-scheme"),
+        }
+    }
+    Ok(())
+}
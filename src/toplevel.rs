@@ -0,0 +1,124 @@
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::wl_registry,
+    Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::AppletError;
+
+// a window of any client, as the compositor reports it.
+#[derive(Clone, Debug, Default)]
+pub struct Toplevel {
+    pub app_id: String,
+    pub activated: bool,
+    pub fullscreen: bool,
+}
+
+struct State<F> {
+    // toplevels by handle, with pending changes until the compositor sends done.
+    toplevels: Vec<(ZwlrForeignToplevelHandleV1, Toplevel, Toplevel)>,
+    changed: F,
+    dirty: bool,
+}
+
+impl<F> Dispatch<wl_registry::WlRegistry, GlobalListContents> for State<F> {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl<F: 'static> Dispatch<ZwlrForeignToplevelManagerV1, ()> for State<F> {
+    fn event(
+        state: &mut Self,
+        _manager: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state
+                .toplevels
+                .push((toplevel, Toplevel::default(), Toplevel::default()));
+        }
+    }
+
+    wayland_client::event_created_child!(State<F>, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl<F> Dispatch<ZwlrForeignToplevelHandleV1, ()> for State<F> {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let Some(index) = state.toplevels.iter().position(|(h, ..)| h == handle) else {
+            return;
+        };
+        let (_, current, pending) = &mut state.toplevels[index];
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => pending.app_id = app_id,
+            // the state is an array of native-endian u32 values.
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: values } => {
+                let values: Vec<u32> = values
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+                pending.activated =
+                    values.contains(&(zwlr_foreign_toplevel_handle_v1::State::Activated as u32));
+                pending.fullscreen =
+                    values.contains(&(zwlr_foreign_toplevel_handle_v1::State::Fullscreen as u32));
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                *current = pending.clone();
+                state.dirty = true;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                handle.destroy();
+                state.toplevels.remove(index);
+                state.dirty = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+// call `changed` with all toplevels each time any of them changes. runs until the
+// wayland connection closes, so it belongs on its own thread. needs a compositor
+// with wlr-foreign-toplevel-management, such as sway or hyprland.
+pub fn watch(changed: impl FnMut(&[Toplevel]) + 'static) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State<_>>(&connection)?;
+    let _manager: ZwlrForeignToplevelManagerV1 = globals.bind(&queue.handle(), 1..=3, ())?;
+    let mut state = State {
+        toplevels: Vec::new(),
+        changed,
+        dirty: false,
+    };
+    loop {
+        queue.blocking_dispatch(&mut state)?;
+        if std::mem::take(&mut state.dirty) {
+            let toplevels: Vec<Toplevel> = state
+                .toplevels
+                .iter()
+                .map(|(_, toplevel, _)| toplevel.clone())
+                .collect();
+            (state.changed)(&toplevels);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, fs::File};
+
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_output, wl_registry},
+    Connection, Dispatch, QueueHandle,
+};
+
+use crate::AppletError;
+
+#[derive(Default)]
+struct Globals {
+    output_names: Vec<Option<String>>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Globals {
+    fn event(
+        _state: &mut Self,
+        _registry: &wl_registry::WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+// outputs by the name the compositor gives them, e.g. "DP-1", from version 4 on.
+impl Dispatch<wl_output::WlOutput, usize> for Globals {
+    fn event(
+        state: &mut Self,
+        _output: &wl_output::WlOutput,
+        event: wl_output::Event,
+        index: &usize,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.output_names[*index] = Some(name);
+        }
+    }
+}
+
+// interface names of all globals the compositor advertises.
+pub fn global_interfaces() -> Result<Vec<String>, AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, _queue) = registry_queue_init::<Globals>(&connection)?;
+    Ok(globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .map(|global| global.interface)
+        .collect())
+}
+
+// names of the compositor's outputs. compositors with wl_output older than
+// version 4 do not name them, so those are left out.
+pub fn output_names() -> Result<Vec<String>, AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Globals>(&connection)?;
+    let outputs: Vec<_> = globals
+        .contents()
+        .clone_list()
+        .into_iter()
+        .filter(|global| global.interface == "wl_output" && global.version >= 4)
+        .collect();
+    let mut state = Globals {
+        output_names: vec![None; outputs.len()],
+    };
+    for (index, global) in outputs.iter().enumerate() {
+        let _output: wl_output::WlOutput =
+            globals
+                .registry()
+                .bind(global.name, 4, &queue.handle(), index);
+    }
+    queue.roundtrip(&mut state)?;
+    Ok(state.output_names.into_iter().flatten().collect())
+}
+
+// outputs by global name, and the changes not yet reported.
+#[derive(Default)]
+struct Hotplug {
+    names: HashMap<u32, String>,
+    // outputs present at startup are named too, but not reported as added.
+    started: bool,
+    changes: Vec<(String, bool)>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Hotplug {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == "wl_output" && version >= 4 => {
+                let _output: wl_output::WlOutput = registry.bind(name, 4, qhandle, name);
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(output) = state.names.remove(&name) {
+                    state.changes.push((output, false));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, u32> for Hotplug {
+    fn event(
+        state: &mut Self,
+        _output: &wl_output::WlOutput,
+        event: wl_output::Event,
+        global: &u32,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            if state.started {
+                state.changes.push((name.clone(), true));
+            }
+            state.names.insert(*global, name);
+        }
+    }
+}
+
+// an unlinked file in the runtime directory to share pixels with the compositor.
+// `purpose` keeps the names of threads doing this at once apart.
+pub fn shm_file(purpose: &str, size: u64) -> std::io::Result<File> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let path = dir.join(format!(
+        "wl-gammarelay-applet-{purpose}-{}",
+        std::process::id()
+    ));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+    file.set_len(size)?;
+    Ok(file)
+}
+
+// the name the daemon gives an output's object, e.g. "DP_1" for "DP-1", as
+// dbus paths cannot hold dashes.
+pub fn daemon_output_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+// call `changed` with the daemon name of each output connected (true) or
+// disconnected (false) from now on. runs until the wayland connection closes,
+// so it belongs on its own thread.
+pub fn watch_outputs(mut changed: impl FnMut(&str, bool)) -> Result<(), AppletError> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<Hotplug>(&connection)?;
+    let qhandle = queue.handle();
+    for global in globals.contents().clone_list() {
+        if global.interface == "wl_output" && global.version >= 4 {
+            let _output: wl_output::WlOutput =
+                globals
+                    .registry()
+                    .bind(global.name, 4, &qhandle, global.name);
+        }
+    }
+    let mut state = Hotplug::default();
+    queue.roundtrip(&mut state)?;
+    state.started = true;
+    loop {
+        queue.blocking_dispatch(&mut state)?;
+        for (name, connected) in state.changes.drain(..) {
+            changed(&daemon_output_name(&name), connected);
+        }
+    }
+}
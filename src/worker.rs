@@ -0,0 +1,622 @@
+use std::{
+    sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+    thread::spawn,
+    time::{Duration, Instant},
+};
+
+use zbus::{
+    blocking::{fdo::PropertiesProxy, Connection},
+    zvariant::ObjectPath,
+};
+
+use crate::{
+    backlight::Backlight,
+    convert::{
+        dbus_brightness_rounded, dbus_gamma_rounded, dbus_temperature_rounded,
+        ui_brightness_delta_to_dbus_value, ui_temperature_delta_to_dbus_value,
+    },
+    daemon::DAEMON_SERVICE,
+    pending::{Deltas, Op, PendingOps},
+    AppletError, GammaRelayProxyBlocking,
+};
+
+// daemon values as last written or read by the worker.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DaemonState {
+    pub inverted: bool,
+    pub grayscale: bool,
+    pub temperature: u16,
+    pub brightness: f64,
+    pub gamma: f64,
+}
+
+impl DaemonState {
+    // the daemon may do its own float arithmetic, so tiny differences are ignored.
+    fn differs(&self, other: &DaemonState) -> bool {
+        self.inverted != other.inverted
+            || self.grayscale != other.grayscale
+            || self.temperature != other.temperature
+            || (self.brightness - other.brightness).abs() > 0.001
+            || (self.gamma - other.gamma).abs() > 0.001
+    }
+}
+
+// the daemon calls the worker makes, so tests can stand in for the daemon.
+pub trait Daemon {
+    fn inverted(&self) -> zbus::Result<bool>;
+    fn toggle_inverted(&self) -> zbus::Result<()>;
+    fn grayscale(&self) -> zbus::Result<bool>;
+    fn set_grayscale(&self, value: bool) -> zbus::Result<()>;
+    fn temperature(&self) -> zbus::Result<u16>;
+    fn update_temperature(&self, delta: i16) -> zbus::Result<()>;
+    fn brightness(&self) -> zbus::Result<f64>;
+    fn update_brightness(&self, delta: f64) -> zbus::Result<()>;
+    fn gamma(&self) -> zbus::Result<f64>;
+    fn update_gamma(&self, delta: f64) -> zbus::Result<()>;
+}
+
+impl Daemon for GammaRelayProxyBlocking<'_> {
+    fn inverted(&self) -> zbus::Result<bool> {
+        GammaRelayProxyBlocking::inverted(self)
+    }
+    fn toggle_inverted(&self) -> zbus::Result<()> {
+        GammaRelayProxyBlocking::toggle_inverted(self)
+    }
+    fn grayscale(&self) -> zbus::Result<bool> {
+        GammaRelayProxyBlocking::grayscale(self)
+    }
+    fn set_grayscale(&self, value: bool) -> zbus::Result<()> {
+        GammaRelayProxyBlocking::set_grayscale(self, value)
+    }
+    fn temperature(&self) -> zbus::Result<u16> {
+        GammaRelayProxyBlocking::temperature(self)
+    }
+    fn update_temperature(&self, delta: i16) -> zbus::Result<()> {
+        GammaRelayProxyBlocking::update_temperature(self, delta)
+    }
+    fn brightness(&self) -> zbus::Result<f64> {
+        GammaRelayProxyBlocking::brightness(self)
+    }
+    fn update_brightness(&self, delta: f64) -> zbus::Result<()> {
+        GammaRelayProxyBlocking::update_brightness(self, delta)
+    }
+    fn gamma(&self) -> zbus::Result<f64> {
+        GammaRelayProxyBlocking::gamma(self)
+    }
+    fn update_gamma(&self, delta: f64) -> zbus::Result<()> {
+        GammaRelayProxyBlocking::update_gamma(self, delta)
+    }
+}
+
+pub struct Options {
+    // older daemons lack invert and gamma, and most lack grayscale; they are read as neutral.
+    pub has_invert: bool,
+    pub has_gamma: bool,
+    pub has_grayscale: bool,
+    pub temperature_rounding: u16,
+    // poll for changes made elsewhere until the daemon is seen to emit change signals.
+    pub poll_interval: Option<Duration>,
+}
+
+enum Request {
+    Write(u64, Op),
+    // the daemon emitted PropertiesChanged.
+    Changed,
+    // while the window is hidden, stop polling and reading the daemon.
+    Park(bool),
+}
+
+pub enum Update {
+    // every request up to `handled` has been written. `consumed` holds the parts
+    // of the last deltas request that were applied.
+    Synced {
+        handled: u64,
+        state: DaemonState,
+        consumed: Deltas,
+    },
+    // writes are queued and will be retried after `retry_in`.
+    Failed {
+        error: zbus::Error,
+        retry_in: Duration,
+    },
+    // something other than the applet changed the daemon values.
+    External(DaemonState),
+}
+
+// the ui side of the worker thread, which owns the daemon proxy so slow DBus
+// calls never block rendering. requests are numbered so the ui can tell which
+// of them an update covers.
+pub struct Worker {
+    requests: Sender<Request>,
+    updates: Receiver<Update>,
+    sent: u64,
+}
+
+impl Worker {
+    // start the worker and read the initial daemon state on it.
+    pub fn spawn(
+        proxy: GammaRelayProxyBlocking<'static>,
+        backlight: Option<Backlight>,
+        options: Options,
+    ) -> Result<(Self, DaemonState), AppletError> {
+        let (requests, request_receiver) = channel();
+        let (update_sender, updates) = channel();
+        let (startup_sender, startup_receiver) = channel();
+
+        let connection = proxy.inner().connection().clone();
+        let path = proxy.inner().path().to_owned();
+        let watch_requests = requests.clone();
+        spawn(move || {
+            if let Err(e) = watch(&connection, path, &watch_requests) {
+                eprintln!("rust: watch daemon change signals: {e}");
+            }
+        });
+
+        spawn(move || {
+            let state = match read_state(&proxy, &options) {
+                Ok(state) => state,
+                Err(e) => {
+                    let _ = startup_sender.send(Err(e));
+                    return;
+                }
+            };
+            let _ = startup_sender.send(Ok(state));
+            run(
+                proxy,
+                backlight,
+                state,
+                options,
+                request_receiver,
+                update_sender,
+            );
+        });
+        let state = startup_receiver
+            .recv()
+            .expect("rust: receive startup daemon state")?;
+        Ok((
+            Self {
+                requests,
+                updates,
+                sent: 0,
+            },
+            state,
+        ))
+    }
+
+    // queue a write and return its sequence number.
+    pub fn send(&mut self, op: Op) -> u64 {
+        self.sent += 1;
+        let _ = self.requests.send(Request::Write(self.sent, op));
+        self.sent
+    }
+
+    pub fn park(&self, parked: bool) {
+        let _ = self.requests.send(Request::Park(parked));
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn try_recv(&self) -> Option<Update> {
+        self.updates.try_recv().ok()
+    }
+}
+
+// forward the daemon's change signals to the worker.
+fn watch(
+    connection: &Connection,
+    path: ObjectPath<'static>,
+    requests: &Sender<Request>,
+) -> zbus::Result<()> {
+    let properties = PropertiesProxy::builder(connection)
+        .destination(DAEMON_SERVICE)?
+        .path(path)?
+        .build()?;
+    for _ in properties.receive_properties_changed()? {
+        if requests.send(Request::Changed).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_state(proxy: &impl Daemon, options: &Options) -> zbus::Result<DaemonState> {
+    Ok(DaemonState {
+        inverted: options.has_invert && proxy.inverted()?,
+        grayscale: options.has_grayscale && proxy.grayscale()?,
+        temperature: proxy.temperature()?,
+        brightness: proxy.brightness()?,
+        gamma: if options.has_gamma {
+            proxy.gamma()?
+        } else {
+            1.0
+        },
+    })
+}
+
+fn run(
+    proxy: GammaRelayProxyBlocking<'static>,
+    backlight: Option<Backlight>,
+    mut state: DaemonState,
+    options: Options,
+    requests: Receiver<Request>,
+    updates: Sender<Update>,
+) {
+    let mut pending = PendingOps::default();
+    let mut handled = 0;
+    let mut consumed = Deltas::default();
+    let mut check_external = false;
+    let mut polling = options.poll_interval.is_some();
+    let mut parked = false;
+    let mut next_poll = options
+        .poll_interval
+        .map(|interval| Instant::now() + interval);
+    loop {
+        // wait for a request, for the backoff to pass if writes are queued,
+        // or for the next poll.
+        let deadline = [pending.retry_at(), next_poll].into_iter().flatten().min();
+        let received = match deadline {
+            Some(deadline) => {
+                requests.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => requests.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        let first = match received {
+            Ok(request) => Some(request),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        for request in first.into_iter().chain(requests.try_iter()) {
+            match request {
+                Request::Write(seq, op) => {
+                    // a newer deltas request replaces the queued one, and so its progress.
+                    if matches!(op, Op::Deltas(_)) {
+                        consumed = Deltas::default();
+                    }
+                    handled = seq;
+                    pending.push(op);
+                }
+                Request::Changed => {
+                    if polling {
+                        eprintln!("rust: daemon emits change signals; polling stopped");
+                    }
+                    polling = false;
+                    next_poll = None;
+                    check_external = true;
+                }
+                Request::Park(park) => {
+                    parked = park;
+                    next_poll = options
+                        .poll_interval
+                        .filter(|_| polling && !parked)
+                        .map(|interval| Instant::now() + interval);
+                    // catch up on anything missed while parked.
+                    check_external |= !parked;
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if next_poll.is_some_and(|next_poll| now >= next_poll) {
+            next_poll = options.poll_interval.map(|interval| now + interval);
+            check_external = true;
+        }
+
+        if !pending.is_empty() && pending.ready(now) {
+            let result = pending.flush(|op| {
+                write_op(
+                    &proxy,
+                    backlight.as_ref(),
+                    &mut state,
+                    op,
+                    &options,
+                    &mut consumed,
+                )
+            });
+            let update = match result {
+                Ok(()) => {
+                    pending.succeeded();
+                    Update::Synced {
+                        handled,
+                        state,
+                        consumed: std::mem::take(&mut consumed),
+                    }
+                }
+                Err(error) => Update::Failed {
+                    error,
+                    retry_in: pending.failed(now),
+                },
+            };
+            if updates.send(update).is_err() {
+                return;
+            }
+        }
+
+        // compare with the daemon once queued writes are done, as signals for
+        // the applet's own writes also arrive here.
+        if check_external && pending.is_empty() && !parked {
+            check_external = false;
+            match read_state(&proxy, &options) {
+                Ok(read) => {
+                    let external = read.differs(&state);
+                    state = read;
+                    if external && updates.send(Update::External(state)).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => eprintln!("rust: read daemon state: {e}"),
+            }
+        }
+    }
+}
+
+// write one queued op. a deltas op keeps what is left of it, and its applied
+// parts are added to `consumed`.
+fn write_op(
+    proxy: &impl Daemon,
+    backlight: Option<&Backlight>,
+    state: &mut DaemonState,
+    op: &mut Op,
+    options: &Options,
+    consumed: &mut Deltas,
+) -> zbus::Result<()> {
+    match op {
+        Op::Deltas(deltas) => {
+            apply_deltas(proxy, state, deltas, options.temperature_rounding, consumed)
+        }
+        Op::Backlight(value) => match backlight {
+            Some(backlight) => backlight.set_value(*value),
+            None => Ok(()),
+        },
+        Op::Refresh => {
+            *state = read_state(proxy, options)?;
+            Ok(())
+        }
+        op => write_value(proxy, state, *op),
+    }
+}
+
+// write an absolute value by applying the lossless delta from the server value.
+pub fn write_value(proxy: &impl Daemon, state: &mut DaemonState, op: Op) -> zbus::Result<()> {
+    match op {
+        Op::Invert(value) => {
+            if proxy.inverted()? != value {
+                proxy.toggle_inverted()?;
+            }
+            state.inverted = value;
+        }
+        Op::Grayscale(value) => {
+            proxy.set_grayscale(value)?;
+            state.grayscale = value;
+        }
+        Op::Temperature(value) => {
+            let server_value = proxy.temperature()? as i16;
+            proxy.update_temperature(value as i16 - server_value)?;
+            state.temperature = value;
+        }
+        Op::Brightness(value) => {
+            let server_value = proxy.brightness()?;
+            proxy.update_brightness(value - server_value)?;
+            state.brightness = value;
+        }
+        Op::Gamma(value) => {
+            let server_value = proxy.gamma()?;
+            proxy.update_gamma(value - server_value)?;
+            state.gamma = value;
+        }
+        Op::Backlight(_) | Op::Deltas(_) | Op::Refresh => {}
+    }
+    Ok(())
+}
+
+// apply slider deltas to the daemon. each applied part is cleared from `deltas`
+// and added to `consumed`, so a retry after a failure does not apply it twice.
+// a delta too small to change the rounded value is left for the ui to accumulate.
+fn apply_deltas(
+    proxy: &impl Daemon,
+    state: &mut DaemonState,
+    deltas: &mut Deltas,
+    temperature_rounding: u16,
+    consumed: &mut Deltas,
+) -> zbus::Result<()> {
+    if deltas.temperature != 0.0 {
+        let server_value = proxy.temperature()? as i16;
+        let dbus_delta = ui_temperature_delta_to_dbus_value(deltas.temperature);
+        let (final_value, clamped_delta) = {
+            let rounded_delta = dbus_temperature_rounded(dbus_delta, temperature_rounding);
+            let proposed_final_value = server_value + rounded_delta;
+            if proposed_final_value < 1000 {
+                (1000, 1000 - server_value)
+            } else if proposed_final_value > 10000 {
+                (10000, 10000 - server_value)
+            } else {
+                (proposed_final_value, rounded_delta)
+            }
+        };
+        if clamped_delta.abs() > 0 {
+            proxy.update_temperature(clamped_delta)?;
+            state.temperature = final_value as u16;
+            consumed.temperature = deltas.temperature;
+        }
+        deltas.temperature = 0.0;
+    }
+
+    if deltas.brightness != 0.0 {
+        let server_value = proxy.brightness()?;
+        let rounded_delta =
+            dbus_brightness_rounded(ui_brightness_delta_to_dbus_value(deltas.brightness));
+        let final_value = server_value + rounded_delta;
+        if final_value > 0.2 && final_value < 1.0 {
+            proxy.update_brightness(rounded_delta)?;
+            state.brightness = final_value;
+            consumed.brightness = deltas.brightness;
+        }
+        deltas.brightness = 0.0;
+    }
+
+    if deltas.gamma != 0.0 {
+        let server_value = proxy.gamma()?;
+        let rounded_delta = dbus_gamma_rounded(deltas.gamma);
+        let final_value = server_value + rounded_delta;
+        if final_value < 1.5 && final_value > 0.5 {
+            proxy.update_gamma(rounded_delta)?;
+            state.gamma = final_value;
+            consumed.gamma = deltas.gamma;
+        }
+        deltas.gamma = 0.0;
+    }
+
+    Ok(())
+}
+
+// stands in for the daemon in tests and --bench-ticks.
+pub mod mock {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    // a daemon in memory, recording the calls made to it by name.
+    pub struct MockDaemon {
+        pub state: RefCell<DaemonState>,
+        pub calls: RefCell<Vec<String>>,
+    }
+
+    impl MockDaemon {
+        fn call(&self, call: String) {
+            self.calls.borrow_mut().push(call);
+        }
+    }
+
+    impl Daemon for MockDaemon {
+        fn inverted(&self) -> zbus::Result<bool> {
+            Ok(self.state.borrow().inverted)
+        }
+        fn toggle_inverted(&self) -> zbus::Result<()> {
+            self.call("ToggleInverted".to_string());
+            let mut state = self.state.borrow_mut();
+            state.inverted = !state.inverted;
+            Ok(())
+        }
+        fn grayscale(&self) -> zbus::Result<bool> {
+            Ok(self.state.borrow().grayscale)
+        }
+        fn set_grayscale(&self, value: bool) -> zbus::Result<()> {
+            self.call(format!("SetGrayscale({value})"));
+            self.state.borrow_mut().grayscale = value;
+            Ok(())
+        }
+        fn temperature(&self) -> zbus::Result<u16> {
+            Ok(self.state.borrow().temperature)
+        }
+        fn update_temperature(&self, delta: i16) -> zbus::Result<()> {
+            self.call(format!("UpdateTemperature({delta})"));
+            let mut state = self.state.borrow_mut();
+            state.temperature = (state.temperature as i32 + delta as i32).clamp(1000, 10000) as u16;
+            Ok(())
+        }
+        fn brightness(&self) -> zbus::Result<f64> {
+            Ok(self.state.borrow().brightness)
+        }
+        fn update_brightness(&self, delta: f64) -> zbus::Result<()> {
+            self.call(format!("UpdateBrightness({delta:.2})"));
+            let mut state = self.state.borrow_mut();
+            state.brightness = (state.brightness + delta).clamp(0.0, 1.0);
+            Ok(())
+        }
+        fn gamma(&self) -> zbus::Result<f64> {
+            Ok(self.state.borrow().gamma)
+        }
+        fn update_gamma(&self, delta: f64) -> zbus::Result<()> {
+            self.call(format!("UpdateGamma({delta:.2})"));
+            self.state.borrow_mut().gamma += delta;
+            Ok(())
+        }
+    }
+
+    // stands in for the worker thread, answering requests on the test's thread
+    // when asked to.
+    pub struct MockWorker {
+        pub daemon: MockDaemon,
+        state: DaemonState,
+        requests: Receiver<Request>,
+        updates: Sender<Update>,
+        options: Options,
+    }
+
+    impl MockWorker {
+        // write all queued requests to the daemon and report, as the worker would.
+        pub fn answer(&mut self) {
+            let mut handled = None;
+            let mut consumed = Deltas::default();
+            for request in self.requests.try_iter() {
+                if let Request::Write(seq, mut op) = request {
+                    if matches!(op, Op::Deltas(_)) {
+                        consumed = Deltas::default();
+                    }
+                    write_op(
+                        &self.daemon,
+                        None,
+                        &mut self.state,
+                        &mut op,
+                        &self.options,
+                        &mut consumed,
+                    )
+                    .expect("rust: write to mock daemon");
+                    handled = Some(seq);
+                }
+            }
+            if let Some(handled) = handled {
+                let _ = self.updates.send(Update::Synced {
+                    handled,
+                    state: self.state,
+                    consumed,
+                });
+            }
+        }
+
+        // change the daemon as another client would, and report it as the
+        // worker does after a change signal.
+        #[cfg(test)]
+        pub fn change_externally(&mut self, change: impl FnOnce(&mut DaemonState)) {
+            change(&mut self.daemon.state.borrow_mut());
+            self.state = *self.daemon.state.borrow();
+            let _ = self.updates.send(Update::External(self.state));
+        }
+
+        // take the calls made since the last time.
+        pub fn calls(&self) -> Vec<String> {
+            self.daemon.calls.take()
+        }
+    }
+
+    impl Worker {
+        pub fn mock(state: DaemonState) -> (Self, MockWorker) {
+            let (requests, request_receiver) = channel();
+            let (update_sender, updates) = channel();
+            (
+                Self {
+                    requests,
+                    updates,
+                    sent: 0,
+                },
+                MockWorker {
+                    daemon: MockDaemon {
+                        state: RefCell::new(state),
+                        calls: RefCell::default(),
+                    },
+                    state,
+                    requests: request_receiver,
+                    updates: update_sender,
+                    options: Options {
+                        has_invert: true,
+                        has_gamma: true,
+                        has_grayscale: true,
+                        temperature_rounding: 100,
+                        poll_interval: None,
+                    },
+                },
+            )
+        }
+    }
+}